@@ -1,6 +1,8 @@
 use std::{fmt, string::ToString};
 
 use rspotify::model::SimplifiedPlaylist;
+
+use crate::{doctor::Check, import::ImportMatch, verify::Broken};
 use serde::{
     de::{self, Deserializer, Visitor},
     Deserialize, Serialize,
@@ -17,24 +19,107 @@ pub enum Action {
     Quit,
     Refresh,
     Error(String),
+    DismissError,
+    Notify(String),
     Help,
 
-    // Home Actions
+    // Setup Actions
+    SubmitCredentials,
+
+    // Navigation Actions
     MoveUp,
     MoveDown,
+    PageUp,
+    PageDown,
+    Top,
+    Bottom,
+    Select,
+    Back,
+    Filter,
+    ToggleMark,
+    MarkAll,
+    InvertMarks,
+
+    // Home Actions
     EnterEditing,
     QuitEditing,
     SelectFolder(String),
     BackHome,
     // Download Actions
     EnterDownloader,
-    SelectPlaylist(usize),
+    CycleSort,
+    ReverseSort,
+    ExportMarked,
+    ExportAll,
+    SelectPlaylists(Vec<usize>),
+    ExportPlaylists(Vec<usize>),
     // Manage Actions
     EnterManager,
     GetDirs(Vec<String>),
-    SelectActivePlaylist(usize),
+    SelectActivePlaylists(Vec<usize>),
+    Rename,
+    Delete,
+    Move,
+    Duplicate,
+    Undo,
+    Verify,
+    Mirror,
+    Transcode,
+    ExportTranscoded,
+    /// Deletes the broken files found by the last verification and downloads
+    /// them again.
+    Repair,
+    RenamePlaylist(usize, String),
+    DeletePlaylist(usize),
+    /// Moves a playlist folder into another library root.
+    MovePlaylist(usize, String),
+    DuplicatePlaylist(usize),
+    VerifyPlaylists(Vec<usize>),
+    /// Mirrors playlist folders to the device mounted at the given path.
+    MirrorPlaylists(Vec<usize>, String),
+    /// Writes transcoded copies of playlist folders to the transcode dir.
+    TranscodePlaylists(Vec<usize>),
+    /// Zips transcoded copies of playlist folders into the transcode dir.
+    ExportTranscodedPlaylists(Vec<usize>),
+    Verified(Vec<Broken>),
+    /// Downloads the tracks of deleted broken files again.
+    Requeue(Vec<Broken>),
+    // Import Actions
+    EnterImporter,
+    ImportFile(String),
+    ImportMatched(Vec<ImportMatch>),
+    CreatePlaylist(String, Vec<String>),
+    // Diagnostics Actions
+    EnterDiagnostics,
+    /// Results of the checks run in the background.
+    Diagnosed(Vec<Check>),
+    // Dashboard Actions
+    EnterDashboard,
+    // Profile Actions
+    EnterProfiles,
+    SwitchProfile(String),
+    ProfileSwitched(String),
+    LoggedIn(String),
+    LoadPlaylists(Vec<SimplifiedPlaylist>),
 
     // Output functions
+    FollowLog,
+    FindNext,
+    FindPrev,
+    SaveLog,
     Downloading(String),
+    /// Jobs finished so far out of the whole batch.
+    Progress(usize, usize),
     DownloadFinished,
+    EnterSearch,
+    QuitSearch,
+
+    // Input Actions
+    Confirm,
+    Cancel,
+    // Folder Actions
+    Complete,
+    Open,
+    Parent,
+    NewFolder,
 }
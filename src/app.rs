@@ -1,85 +1,279 @@
 use color_eyre::eyre::Result;
 use crossterm::event::KeyEvent;
 use ratatui::{prelude::*, widgets::*};
+use rspotify::Credentials;
 use serde::{Deserialize, Serialize};
-use std::rc::Rc;
-use tokio::sync::mpsc;
+use std::{rc::Rc, time::Instant};
+use tokio::sync::mpsc::{self, UnboundedSender};
 
 use crate::{
     action::Action,
+    chord::{Chord, ChordResult},
     components::{
-        download::Download, fps::FpsCounter, home::Home, manager::Manager, spotify::Spotify,
+        dashboard::Dashboard,
+        diagnostics::Diagnostics,
+        download::Download,
+        folder_picker::FolderPicker,
+        fps::FpsCounter,
+        help::Help,
+        home::Home,
+        importer::Importer,
+        manager::Manager,
+        popup::ErrorPopup,
+        profiles::Profiles,
+        setup::Setup,
+        spotify::{validate_credentials, Spotify},
+        status::StatusBar,
+        which_key::WhichKey,
         Component,
     },
-    config::Config,
+    config::{
+        check_config, save_credentials, Config, ConfigIssue, ConfigWatcher, Profile, SpotifyConfig,
+    },
     mode::Mode,
+    screen::{screen, ComponentId, Registry},
     tui,
 };
 
 pub struct App {
     pub config: Config,
+    /// Problems found in the config files, shown in a banner.
+    pub config_issues: Vec<ConfigIssue>,
+    pub config_watcher: ConfigWatcher,
+    pub profile: Profile,
+    pub music_dir: String,
     pub tick_rate: f64,
     pub frame_rate: f64,
-    pub components: Vec<Box<dyn Component>>,
-    pub displays: Vec<Box<dyn Component>>,
+    pub registry: Registry,
+    pub error_popup: ErrorPopup,
+    pub help: Help,
+    pub status_bar: StatusBar,
+    pub which_key: WhichKey,
     pub should_quit: bool,
     pub should_suspend: bool,
     pub mode: Mode,
-    pub last_tick_key_events: Vec<KeyEvent>,
+    pub mode_stack: Vec<Mode>,
+    pub chord: Chord,
 }
 
 impl App {
-    pub fn new(tick_rate: f64, frame_rate: f64, spotify: Spotify) -> Result<Self> {
+    pub fn new(
+        tick_rate: f64,
+        frame_rate: f64,
+        config: Config,
+        profile: Profile,
+        spotify: Spotify,
+        config_issues: Vec<ConfigIssue>,
+    ) -> Result<Self> {
         let home = Home::new(spotify.playlists.clone());
+        let user = spotify.user.clone();
         let manager = Manager::new();
         let fps = FpsCounter::default();
-        let config = Config::new()?;
         let download = Download::new();
-        let mode = Mode::Input;
+        let importer = Importer::new();
+        let diagnostics = Diagnostics::new();
+        let profiles = Profiles::new();
+        let mode = Mode::Folder;
+        let mut registry = Registry::new();
+        registry.register(ComponentId::Home, Box::new(home));
+        registry.register(ComponentId::Fps, Box::new(fps));
+        registry.register(ComponentId::FolderPicker, Box::new(FolderPicker::new()));
+        registry.register(ComponentId::Download, Box::new(download));
+        registry.register(ComponentId::Importer, Box::new(importer));
+        registry.register(ComponentId::Diagnostics, Box::new(diagnostics));
+        registry.register(ComponentId::Dashboard, Box::new(Dashboard::new()));
+        registry.register(ComponentId::Profiles, Box::new(profiles));
+        registry.register(ComponentId::Manager, Box::new(manager));
+        registry.register(ComponentId::Spotify, Box::new(spotify));
+        registry.focus_base(screen(mode).focus);
         Ok(Self {
             tick_rate,
             frame_rate,
-            components: vec![Box::new(home), Box::new(fps), Box::new(download)],
-            displays: vec![Box::new(manager), Box::new(spotify)],
+            registry,
+            error_popup: ErrorPopup::new(),
+            help: Help::new(),
+            status_bar: StatusBar::new(user),
+            which_key: WhichKey::new(),
             should_quit: false,
             should_suspend: false,
             config,
+            config_issues,
+            config_watcher: ConfigWatcher::new(),
+            profile,
+            music_dir: String::new(),
             mode,
-            last_tick_key_events: Vec::new(),
+            mode_stack: Vec::new(),
+            chord: Chord::default(),
         })
     }
 
+    /// The mode of the screen underneath any open prompt or popup.
+    fn base_mode(&self) -> Mode {
+        self.mode_stack.first().copied().unwrap_or(self.mode)
+    }
+
+    /// Switches the underlying mode, keeping any open prompt or popup on top of it.
+    fn switch_mode(&mut self, mode: Mode) {
+        match self.mode_stack.first_mut() {
+            Some(base) => *base = mode,
+            None => self.mode = mode,
+        }
+        self.registry.focus_base(screen(mode).focus);
+    }
+
+    /// Opens a prompt or popup on top of the current mode.
+    fn push_mode(&mut self, mode: Mode) {
+        self.mode_stack.push(self.mode);
+        self.mode = mode;
+        self.registry.push_focus(screen(mode).focus);
+    }
+
+    /// Closes the topmost prompt or popup and returns to the mode below it.
+    fn pop_mode(&mut self) {
+        if let Some(mode) = self.mode_stack.pop() {
+            self.mode = mode;
+            self.registry.pop_focus();
+        }
+    }
+
+    /// Hands the current config to every component.
+    fn register_config(&mut self) -> Result<()> {
+        for component in self.registry.iter_mut() {
+            component.register_config_handler(self.config.clone())?;
+        }
+        self.help.register_config_handler(self.config.clone())?;
+        self.error_popup
+            .register_config_handler(self.config.clone())?;
+        self.status_bar
+            .register_config_handler(self.config.clone())?;
+        self.which_key
+            .register_config_handler(self.config.clone())?;
+        self.chord.set_timeout(self.config.chord_timeout());
+        Ok(())
+    }
+
+    /// Rebuilds the config after its files changed. An invalid config is reported
+    /// and the running one kept, so a half-written edit never breaks the app.
+    fn reload_config(&mut self, action_tx: &UnboundedSender<Action>) -> Result<()> {
+        let issues = check_config();
+        if !issues.is_empty() {
+            let issues = issues.iter().map(ToString::to_string).collect::<Vec<_>>();
+            action_tx.send(Action::Error(format!(
+                "Config not reloaded:\n{}",
+                issues.join("\n")
+            )))?;
+            return Ok(());
+        }
+        match Config::new() {
+            Ok(config) => {
+                self.config = config;
+                self.config_issues.clear();
+                self.register_config()?;
+                action_tx.send(Action::Notify("Config reloaded".into()))?;
+            }
+            Err(err) => action_tx.send(Action::Error(format!("Config not reloaded: {:#}", err)))?,
+        }
+        Ok(())
+    }
+
+    /// Logs in with another profile and swaps in its Spotify session. The terminal
+    /// is released meanwhile since the OAuth flow may prompt on stdin.
+    async fn switch_profile(
+        &mut self,
+        name: &str,
+        tui: &mut tui::Tui,
+        action_tx: &UnboundedSender<Action>,
+    ) -> Result<()> {
+        let profile = self.config.profile(name)?;
+        tui.exit()?;
+        let spotify = Spotify::new(&profile).await;
+        *tui = tui::Tui::new()?
+            .tick_rate(self.tick_rate)
+            .frame_rate(self.frame_rate)
+            .mouse(self.config.config.mouse);
+        tui.enter()?;
+        let mut spotify = spotify?;
+
+        spotify.register_action_handler(action_tx.clone())?;
+        spotify.register_config_handler(self.config.clone())?;
+        spotify.init(tui.size()?)?;
+        action_tx.send(Action::LoadPlaylists(spotify.playlists.clone()))?;
+        action_tx.send(Action::LoggedIn(spotify.user.clone()))?;
+        self.registry
+            .register(ComponentId::Spotify, Box::new(spotify));
+
+        action_tx.send(Action::ProfileSwitched(profile.name.clone()))?;
+        action_tx.send(Action::BackHome)?;
+        let dir = profile.music_dir.clone().unwrap_or(self.music_dir.clone());
+        action_tx.send(Action::SelectFolder(dir))?;
+        self.profile = profile;
+        Ok(())
+    }
+
+    /// Draws the screen of the current mode with the overlays on top of it.
+    fn render(&mut self, tui: &mut tui::Tui, action_tx: &UnboundedSender<Action>) -> Result<()> {
+        let base_mode = self.base_mode();
+        let continuations = self
+            .config
+            .keybindings
+            .get(&self.mode)
+            .map(|keymap| self.chord.continuations(keymap))
+            .unwrap_or_default();
+        self.which_key
+            .show(self.mode, self.chord.pending(), continuations);
+        self.status_bar.set_mode(base_mode);
+        let mut errors = vec![];
+        tui.draw(|f| {
+            let layout = main_layout(f.size());
+            f.render_widget(
+                Block::new()
+                    .borders(Borders::TOP)
+                    .title(screen_title(base_mode)),
+                layout[0],
+            );
+            f.render_widget(config_banner(&self.config, &self.config_issues), layout[0]);
+            for id in screen(base_mode).components {
+                let Some(component) = self.registry.get_mut(*id) else {
+                    continue;
+                };
+                if let Err(e) = component.draw(f, layout[1]) {
+                    errors.push(format!("Failed to draw {}: {:?}", id, e));
+                }
+            }
+            let _ = self.status_bar.draw(f, layout[2]);
+            f.render_widget(self.help.hint_line(self.mode), layout[3]);
+            let _ = self.which_key.draw(f, layout[1]);
+            let _ = self.help.draw(f, f.size());
+            let _ = self.error_popup.draw(f, f.size());
+        })?;
+        for error in errors {
+            action_tx.send(Action::Error(error))?;
+        }
+        Ok(())
+    }
+
     pub async fn run(&mut self) -> Result<()> {
         let (action_tx, mut action_rx) = mpsc::unbounded_channel();
 
         let mut tui = tui::Tui::new()?
             .tick_rate(self.tick_rate)
-            .frame_rate(self.frame_rate);
-        // tui.mouse(true);
+            .frame_rate(self.frame_rate)
+            .mouse(self.config.config.mouse);
         tui.enter()?;
 
-        for component in self.components.iter_mut() {
+        for component in self.registry.iter_mut() {
             component.register_action_handler(action_tx.clone())?;
         }
 
-        for component in self.components.iter_mut() {
-            component.register_config_handler(self.config.clone())?;
-        }
+        self.register_config()?;
 
-        for component in self.components.iter_mut() {
+        for component in self.registry.iter_mut() {
             component.init(tui.size()?)?;
         }
 
-        for display in self.displays.iter_mut() {
-            display.register_action_handler(action_tx.clone())?;
-        }
-
-        for display in self.displays.iter_mut() {
-            display.register_config_handler(self.config.clone())?;
-        }
-
-        for display in self.displays.iter_mut() {
-            display.init(tui.size()?)?;
+        action_tx.send(Action::ProfileSwitched(self.profile.name.clone()))?;
+        if let Some(dir) = &self.profile.music_dir {
+            action_tx.send(Action::SelectFolder(dir.clone()))?;
         }
 
         loop {
@@ -89,43 +283,38 @@ impl App {
                     tui::Event::Tick => action_tx.send(Action::Tick)?,
                     tui::Event::Render => action_tx.send(Action::Render)?,
                     tui::Event::Resize(x, y) => action_tx.send(Action::Resize(x, y))?,
-                    tui::Event::Key(key) => {
-                        if let Some(keymap) = self.config.keybindings.get(&self.mode) {
-                            if let Some(action) = keymap.get(&vec![key]) {
-                                log::info!("Got action: {action:?}");
-                                action_tx.send(action.clone())?;
-                            } else {
-                                // If the key was not handled as a single key action,
-                                // then consider it for multi-key combinations.
-                                self.last_tick_key_events.push(key);
-
-                                // Check for multi-key combinations
-                                if let Some(action) = keymap.get(&self.last_tick_key_events) {
+                    tui::Event::Key(key) if self.mode != Mode::Error => {
+                        match self.config.keybindings.get(&self.mode) {
+                            Some(keymap) => {
+                                let result = self.chord.feed(keymap, key, Instant::now());
+                                if let ChordResult::Matched(action) = result {
                                     log::info!("Got action: {action:?}");
-                                    action_tx.send(action.clone())?;
+                                    action_tx.send(action)?;
                                 }
                             }
+                            None => self.chord.reset(),
                         };
                     }
                     _ => {}
                 }
-                for component in self.components.iter_mut() {
-                    if let Some(action) = component.handle_events(Some(e.clone()))? {
+                // A chord whose next key never came falls back to its own binding
+                if let Some(keymap) = self.config.keybindings.get(&self.mode) {
+                    if let Some(action) = self.chord.expire(keymap, Instant::now()) {
+                        log::info!("Got action: {action:?}");
                         action_tx.send(action)?;
                     }
                 }
-                match self.mode {
-                    Mode::Manager => {
-                        if let Some(action) = self.displays[0].handle_events(Some(e.clone()))? {
-                            action_tx.send(action)?;
-                        }
-                    }
-                    Mode::Downloader => {
-                        if let Some(action) = self.displays[1].handle_events(Some(e.clone()))? {
-                            action_tx.send(action)?;
-                        }
-                    }
-                    _ => {}
+                if self.mode == Mode::Error {
+                    // The error popup is modal, nothing else sees input until it is dismissed
+                    forward(&action_tx, self.error_popup.handle_events(Some(e.clone())))?;
+                } else if self.mode == Mode::Help {
+                    // The help overlay is only closed through its keybindings
+                } else if let Some(component) = self
+                    .registry
+                    .focused()
+                    .and_then(|id| self.registry.get_mut(id))
+                {
+                    forward(&action_tx, component.handle_events(Some(e.clone())))?;
                 }
             }
 
@@ -134,95 +323,85 @@ impl App {
                     log::debug!("{action:?}");
                 }
                 match action {
-                    Action::Tick => {
-                        self.last_tick_key_events.drain(..);
+                    Action::Tick if self.config_watcher.changed() => {
+                        self.reload_config(&action_tx)?;
+                        tui.set_mouse(self.config.config.mouse)?;
+                    }
+                    Action::SelectFolder(ref dir) => {
+                        self.music_dir = dir.clone();
+                        self.switch_mode(Mode::Home);
+                    }
+                    Action::EnterEditing => self.switch_mode(Mode::Folder),
+                    Action::QuitEditing => self.switch_mode(Mode::Home),
+                    Action::EnterDownloader => self.switch_mode(Mode::Downloader),
+                    Action::EnterManager => self.switch_mode(Mode::Manager),
+                    Action::EnterImporter => self.switch_mode(Mode::Importer),
+                    Action::EnterDiagnostics => self.switch_mode(Mode::Diagnostics),
+                    Action::EnterDashboard => self.switch_mode(Mode::Dashboard),
+                    Action::EnterProfiles => self.switch_mode(Mode::Profiles),
+                    Action::SwitchProfile(ref name) => {
+                        if let Err(err) = self.switch_profile(name, &mut tui, &action_tx).await {
+                            action_tx.send(Action::Error(format!(
+                                "Failed to switch to profile `{}`: {:#}",
+                                name, err
+                            )))?;
+                        }
+                    }
+                    Action::DownloadFinished => self.switch_mode(Mode::Waiting),
+                    Action::BackHome => self.switch_mode(Mode::Home),
+                    Action::Help if self.mode == Mode::Help => {
+                        self.help.hide();
+                        self.pop_mode();
+                    }
+                    Action::Help if !matches!(self.mode, Mode::Input | Mode::Error) => {
+                        self.help.show(self.mode);
+                        self.push_mode(Mode::Help);
+                    }
+                    Action::Back if self.mode == Mode::Help => {
+                        self.help.hide();
+                        self.pop_mode();
                     }
-                    Action::SelectFolder(_) => self.mode = Mode::Home,
-                    Action::EnterEditing => self.mode = Mode::Input,
-                    Action::EnterDownloader => self.mode = Mode::Downloader,
-                    Action::EnterManager => self.mode = Mode::Manager,
-                    Action::DownloadFinished => self.mode = Mode::Waiting,
-                    Action::BackHome => self.mode = Mode::Home,
+                    Action::Back
+                        if !matches!(self.mode, Mode::Home | Mode::Input | Mode::Downloading) =>
+                    {
+                        action_tx.send(Action::BackHome)?
+                    }
+                    Action::SelectPlaylists(_)
+                    | Action::SelectActivePlaylists(_)
+                    | Action::VerifyPlaylists(_)
+                    | Action::MirrorPlaylists(_, _)
+                    | Action::TranscodePlaylists(_)
+                    | Action::ExportTranscodedPlaylists(_)
+                    | Action::Requeue(_)
+                    | Action::ExportPlaylists(_)
+                    | Action::CreatePlaylist(_, _) => self.switch_mode(Mode::Downloading),
+                    Action::EnterSearch => self.push_mode(Mode::Input),
+                    Action::QuitSearch => self.pop_mode(),
+                    Action::Error(_) if self.mode != Mode::Error => self.push_mode(Mode::Error),
+                    Action::DismissError => self.pop_mode(),
                     Action::Quit => self.should_quit = true,
                     Action::Suspend => self.should_suspend = true,
                     Action::Resume => self.should_suspend = false,
                     Action::Resize(w, h) => {
                         tui.resize(Rect::new(0, 0, w, h))?;
-                        tui.draw(|f| {
-                            f.render_widget(
-                                Block::new()
-                                    .borders(Borders::TOP)
-                                    .title("Select a playlist to download"),
-                                main_layout(f.size())[0],
-                            );
-                            for component in self.components.iter_mut() {
-                                let r = component.draw(f, main_layout(f.size())[1]);
-                                if let Err(e) = r {
-                                    action_tx
-                                        .send(Action::Error(format!("Failed to draw: {:?}", e)))
-                                        .unwrap();
-                                }
-                            }
-                            let r = match self.mode {
-                                Mode::Manager => self.displays[0].draw(f, main_layout(f.size())[1]),
-                                Mode::Downloader => {
-                                    self.displays[1].draw(f, main_layout(f.size())[1])
-                                }
-                                _ => Ok(()),
-                            };
-                            if let Err(e) = r {
-                                action_tx
-                                    .send(Action::Error(format!("Failed to draw: {:?}", e)))
-                                    .unwrap();
-                            }
-                        })?;
-                    }
-                    Action::Render => {
-                        tui.draw(|f| {
-                            f.render_widget(
-                                Block::new()
-                                    .borders(Borders::TOP)
-                                    .title("Select a playlist to download"),
-                                main_layout(f.size())[0],
-                            );
-                            for component in self.components.iter_mut() {
-                                let r = component.draw(f, main_layout(f.size())[1]);
-                                if let Err(e) = r {
-                                    action_tx
-                                        .send(Action::Error(format!("Failed to draw: {:?}", e)))
-                                        .unwrap();
-                                }
-                            }
-                            for display in self.displays.iter_mut() {
-                                let r = display.draw(f, main_layout(f.size())[1]);
-                                if let Err(e) = r {
-                                    action_tx
-                                        .send(Action::Error(format!("Failed to draw: {:?}", e)))
-                                        .unwrap();
-                                }
-                            }
-                        })?;
+                        self.render(&mut tui, &action_tx)?;
                     }
+                    Action::Render => self.render(&mut tui, &action_tx)?,
                     _ => {}
                 }
-                for component in self.components.iter_mut() {
-                    if let Some(action) = component.update(action.clone())? {
-                        action_tx.send(action)?
-                    };
-                }
-                for display in self.displays.iter_mut() {
-                    if let Some(action) = display.update(action.clone())? {
-                        action_tx.send(action)?
-                    };
+                for component in self.registry.iter_mut() {
+                    forward(&action_tx, component.update(action.clone()))?;
                 }
+                forward(&action_tx, self.error_popup.update(action.clone()))?;
+                forward(&action_tx, self.status_bar.update(action.clone()))?;
             }
             if self.should_suspend {
                 tui.suspend()?;
                 action_tx.send(Action::Resume)?;
                 tui = tui::Tui::new()?
                     .tick_rate(self.tick_rate)
-                    .frame_rate(self.frame_rate);
-                // tui.mouse(true);
+                    .frame_rate(self.frame_rate)
+                    .mouse(self.config.config.mouse);
                 tui.enter()?;
             } else if self.should_quit {
                 tui.stop()?;
@@ -234,6 +413,104 @@ impl App {
     }
 }
 
+/// Runs the first-run setup wizard until valid Spotify credentials have been entered
+/// and saved. Returns `None` if the user quit instead.
+pub async fn run_setup_wizard(
+    tick_rate: f64,
+    frame_rate: f64,
+    profile: &str,
+) -> Result<Option<SpotifyConfig>> {
+    let mut setup = Setup::new();
+    let mut tui = tui::Tui::new()?.tick_rate(tick_rate).frame_rate(frame_rate);
+    tui.enter()?;
+
+    let result = loop {
+        let Some(e) = tui.next().await else {
+            break None;
+        };
+        let action = match e {
+            tui::Event::Resize(w, h) => {
+                tui.resize(Rect::new(0, 0, w, h))?;
+                None
+            }
+            tui::Event::Render => {
+                tui.draw(|f| {
+                    let _ = setup.draw(f, f.size());
+                })?;
+                None
+            }
+            e => setup.handle_events(Some(e))?,
+        };
+        match action {
+            Some(Action::Quit) => break None,
+            Some(Action::SubmitCredentials) => {
+                let settings = setup.spotify_config();
+                setup.set_validating();
+                tui.draw(|f| {
+                    let _ = setup.draw(f, f.size());
+                })?;
+                let id = settings.client_id.clone().unwrap_or_default();
+                let secret = settings.client_secret.clone().unwrap_or_default();
+                match validate_credentials(Credentials::new(&id, &secret)).await {
+                    Ok(()) => {
+                        save_credentials(profile, &settings)?;
+                        break Some(settings);
+                    }
+                    Err(err) => {
+                        setup.set_error(format!("Spotify rejected the credentials: {}", err))
+                    }
+                }
+            }
+            _ => {}
+        }
+    };
+
+    tui.exit()?;
+    Ok(result)
+}
+
+/// Sends the action produced by a component, reporting its failure as an
+/// `Action::Error` instead of tearing down the terminal.
+fn forward(tx: &UnboundedSender<Action>, result: Result<Option<Action>>) -> Result<()> {
+    match result {
+        Ok(Some(action)) => tx.send(action)?,
+        Ok(None) => {}
+        Err(err) => tx.send(Action::Error(format!("{:#}", err)))?,
+    }
+    Ok(())
+}
+
+/// Right aligned warning about config problems, empty if there are none.
+fn config_banner(config: &Config, issues: &[ConfigIssue]) -> Paragraph<'static> {
+    let text = match issues.len() {
+        0 => String::new(),
+        n => format!(
+            "{} config problem(s), defaults used instead. Run `spotia config check` ",
+            n
+        ),
+    };
+    Paragraph::new(text)
+        .style(config.styles.get_style(Mode::Home, "warn"))
+        .alignment(Alignment::Right)
+}
+
+/// Header title of the screen shown in `mode`.
+fn screen_title(mode: Mode) -> &'static str {
+    match mode {
+        Mode::Home => "Home",
+        Mode::Folder => "Choose your music folder",
+        Mode::Downloader => "Select a playlist to download",
+        Mode::Manager => "Manage downloaded playlists",
+        Mode::Downloading => "Working...",
+        Mode::Waiting => "Done",
+        Mode::Importer => "Import a playlist",
+        Mode::Diagnostics => "Diagnostics",
+        Mode::Dashboard => "Library statistics",
+        Mode::Profiles => "Switch profile",
+        Mode::Input | Mode::Error | Mode::Help | Mode::Idle => "",
+    }
+}
+
 fn main_layout(size: Rect) -> Rc<[Rect]> {
     Layout::new(
         Direction::Vertical,
@@ -241,6 +518,7 @@ fn main_layout(size: Rect) -> Rc<[Rect]> {
             Constraint::Length(1),
             Constraint::Min(0),
             Constraint::Length(1),
+            Constraint::Length(1),
         ],
     )
     .split(size)
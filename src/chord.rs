@@ -0,0 +1,239 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crossterm::event::KeyEvent;
+
+use crate::action::Action;
+
+/// How long a chord waits for its next key unless the config says otherwise.
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_millis(1000);
+
+/// What a key did to the chord in progress.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChordResult {
+    /// The keys so far complete a binding that nothing longer starts with.
+    Matched(Action),
+    /// The keys so far start a longer binding, so more keys are awaited.
+    Pending,
+    /// No binding starts with the keys so far.
+    NoMatch,
+}
+
+/// Collects the keys of multi-key bindings until they match, stop matching, or
+/// time out.
+///
+/// A sequence that is both a binding and the start of a longer one, like `<g>`
+/// next to `<g><g>`, waits for the next key and fires on its own once the
+/// timeout passes.
+#[derive(Debug, Clone)]
+pub struct Chord {
+    pending: Vec<KeyEvent>,
+    last_key: Option<Instant>,
+    timeout: Duration,
+}
+
+impl Default for Chord {
+    fn default() -> Self {
+        Self::new(DEFAULT_TIMEOUT)
+    }
+}
+
+impl Chord {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            pending: Vec::new(),
+            last_key: None,
+            timeout,
+        }
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    /// The keys of the chord in progress.
+    pub fn pending(&self) -> &[KeyEvent] {
+        &self.pending
+    }
+
+    pub fn reset(&mut self) {
+        self.pending.clear();
+        self.last_key = None;
+    }
+
+    /// Adds `key` to the chord and looks the keys so far up in `keymap`.
+    pub fn feed(
+        &mut self,
+        keymap: &HashMap<Vec<KeyEvent>, Action>,
+        key: KeyEvent,
+        now: Instant,
+    ) -> ChordResult {
+        self.pending.push(key);
+        match self.lookup(keymap) {
+            ChordResult::NoMatch if self.pending.len() > 1 => {
+                // The key broke off the chord, it may still start a new one
+                self.reset();
+                self.feed(keymap, key, now)
+            }
+            ChordResult::Pending => {
+                self.last_key = Some(now);
+                ChordResult::Pending
+            }
+            result => {
+                self.reset();
+                result
+            }
+        }
+    }
+
+    /// Ends a chord whose next key did not come in time, returning the action
+    /// bound to the keys typed so far, if any.
+    pub fn expire(
+        &mut self,
+        keymap: &HashMap<Vec<KeyEvent>, Action>,
+        now: Instant,
+    ) -> Option<Action> {
+        let last_key = self.last_key?;
+        if now.duration_since(last_key) < self.timeout {
+            return None;
+        }
+        let action = keymap.get(&self.pending).cloned();
+        self.reset();
+        action
+    }
+
+    /// Bindings that start with the keys of the chord in progress, with those keys
+    /// stripped, sorted by the keys left to type.
+    pub fn continuations(
+        &self,
+        keymap: &HashMap<Vec<KeyEvent>, Action>,
+    ) -> Vec<(Vec<KeyEvent>, Action)> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+        let mut continuations = keymap
+            .iter()
+            .filter(|(keys, _)| keys.len() > self.pending.len() && keys.starts_with(&self.pending))
+            .map(|(keys, action)| (keys[self.pending.len()..].to_vec(), action.clone()))
+            .collect::<Vec<_>>();
+        continuations.sort_by_key(|(keys, _)| crate::config::key_sequence_to_string(keys));
+        continuations
+    }
+
+    fn lookup(&self, keymap: &HashMap<Vec<KeyEvent>, Action>) -> ChordResult {
+        let longer = keymap
+            .keys()
+            .any(|keys| keys.len() > self.pending.len() && keys.starts_with(&self.pending));
+        match keymap.get(&self.pending) {
+            _ if longer => ChordResult::Pending,
+            Some(action) => ChordResult::Matched(action.clone()),
+            None => ChordResult::NoMatch,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::config::parse_key_sequence;
+
+    fn keymap(bindings: &[(&str, Action)]) -> HashMap<Vec<KeyEvent>, Action> {
+        bindings
+            .iter()
+            .map(|(keys, action)| (parse_key_sequence(keys).unwrap(), action.clone()))
+            .collect()
+    }
+
+    fn key(raw: &str) -> KeyEvent {
+        parse_key_sequence(raw).unwrap()[0]
+    }
+
+    #[test]
+    fn test_ambiguous_prefix_waits_for_next_key() {
+        let keymap = keymap(&[("<g>", Action::Top), ("<g><g>", Action::Bottom)]);
+        let mut chord = Chord::default();
+        let now = Instant::now();
+
+        assert_eq!(chord.feed(&keymap, key("<g>"), now), ChordResult::Pending);
+        assert_eq!(
+            chord.feed(&keymap, key("<g>"), now),
+            ChordResult::Matched(Action::Bottom)
+        );
+        assert!(chord.pending().is_empty());
+    }
+
+    #[test]
+    fn test_ambiguous_prefix_fires_on_timeout() {
+        let keymap = keymap(&[("<g>", Action::Top), ("<g><g>", Action::Bottom)]);
+        let mut chord = Chord::new(Duration::from_millis(500));
+        let now = Instant::now();
+
+        assert_eq!(chord.feed(&keymap, key("<g>"), now), ChordResult::Pending);
+        assert_eq!(
+            chord.expire(&keymap, now + Duration::from_millis(100)),
+            None
+        );
+        assert_eq!(
+            chord.expire(&keymap, now + Duration::from_millis(500)),
+            Some(Action::Top)
+        );
+        assert!(chord.pending().is_empty());
+    }
+
+    #[test]
+    fn test_prefix_without_binding_is_dropped_on_timeout() {
+        let keymap = keymap(&[("<d><d>", Action::Quit)]);
+        let mut chord = Chord::new(Duration::from_millis(500));
+        let now = Instant::now();
+
+        assert_eq!(chord.feed(&keymap, key("<d>"), now), ChordResult::Pending);
+        assert_eq!(chord.expire(&keymap, now + Duration::from_secs(1)), None);
+        assert!(chord.pending().is_empty());
+    }
+
+    #[test]
+    fn test_broken_chord_restarts_with_last_key() {
+        let keymap = keymap(&[
+            ("<g><g>", Action::Top),
+            ("<j>", Action::MoveDown),
+            ("<z><z>", Action::Select),
+        ]);
+        let mut chord = Chord::default();
+        let now = Instant::now();
+
+        assert_eq!(chord.feed(&keymap, key("<g>"), now), ChordResult::Pending);
+        assert_eq!(
+            chord.feed(&keymap, key("<j>"), now),
+            ChordResult::Matched(Action::MoveDown)
+        );
+        assert_eq!(chord.feed(&keymap, key("<g>"), now), ChordResult::Pending);
+        assert_eq!(chord.feed(&keymap, key("<z>"), now), ChordResult::Pending);
+        assert_eq!(chord.pending(), &[key("<z>")]);
+        assert_eq!(chord.feed(&keymap, key("<x>"), now), ChordResult::NoMatch);
+    }
+
+    #[test]
+    fn test_continuations_strip_the_pending_keys() {
+        let keymap = keymap(&[
+            ("<g>", Action::Top),
+            ("<g><g>", Action::Bottom),
+            ("<g><e>", Action::Select),
+            ("<q>", Action::Quit),
+        ]);
+        let mut chord = Chord::default();
+        assert!(chord.continuations(&keymap).is_empty());
+
+        chord.feed(&keymap, key("<g>"), Instant::now());
+        assert_eq!(
+            chord.continuations(&keymap),
+            vec![
+                (vec![key("<e>")], Action::Select),
+                (vec![key("<g>")], Action::Bottom),
+            ]
+        );
+    }
+}
@@ -1,8 +1,8 @@
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
-use crate::utils::version;
+use crate::{config::DEFAULT_PROFILE, export::ExportFormat, utils::version};
 
 #[derive(Parser, Debug)]
 #[command(author, version = version(), about)]
@@ -24,4 +24,57 @@ pub struct Cli {
         default_value_t = 4.0
     )]
     pub frame_rate: f64,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Profile to use from the config",
+        default_value = DEFAULT_PROFILE
+    )]
+    pub profile: String,
+
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Export playlist metadata without starting the TUI
+    Export {
+        #[arg(
+            short = 'F',
+            long,
+            value_enum,
+            help = "Output format",
+            default_value_t = ExportFormat::Csv
+        )]
+        format: ExportFormat,
+
+        #[arg(short, long, value_name = "FILE", help = "File to write the export to")]
+        output: PathBuf,
+
+        #[arg(
+            short,
+            long = "playlist",
+            value_name = "NAME",
+            help = "Playlist to export, can be repeated (defaults to all playlists)"
+        )]
+        playlists: Vec<String>,
+    },
+    /// Inspect the configuration
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+    /// Check that external tools, credentials and the music directory are usable
+    Doctor {
+        #[arg(short, long, value_name = "DIR", help = "Music directory to check")]
+        music_dir: Option<PathBuf>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ConfigCommand {
+    /// Report every problem in the config files without starting the TUI
+    Check,
 }
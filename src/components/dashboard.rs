@@ -0,0 +1,225 @@
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{Component, Frame};
+use crate::{
+    action::Action,
+    config::{Config, Styles},
+    doctor::format_size,
+    mode::Mode,
+    stats::{format_duration, LibraryStats, HISTORY_DAYS},
+};
+
+/// Bars shown in the charts before the rest is left out.
+const MAX_BARS: usize = 10;
+
+/// Statistics about the playlists downloaded to the music directory.
+#[derive(Default)]
+pub struct Dashboard {
+    mode: Mode,
+    dir: String,
+    stats: LibraryStats,
+    styles: Styles,
+}
+
+impl Dashboard {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Idle,
+            ..Default::default()
+        }
+    }
+
+    fn refresh(&mut self) -> Option<Action> {
+        match LibraryStats::scan(Path::new(&self.dir), SystemTime::now()) {
+            Ok(stats) => {
+                self.stats = stats;
+                None
+            }
+            Err(err) => Some(Action::Error(format!(
+                "Failed to read {}: {}",
+                self.dir, err
+            ))),
+        }
+    }
+
+    fn style(&self, key: &str) -> Style {
+        self.styles.get_style(Mode::Dashboard, key)
+    }
+
+    fn draw_summary(&self, f: &mut Frame<'_>, area: Rect) {
+        let stats = &self.stats;
+        let lines = vec![
+            Line::from(format!(
+                "{} tracks in {} playlists, {}, {}",
+                stats.tracks(),
+                stats.playlists.len(),
+                format_size(stats.bytes()),
+                format_duration(Duration::from_secs(stats.seconds())),
+            )),
+            Line::from(format!(
+                "{} songs are in more than one playlist",
+                stats.duplicates.len()
+            )),
+        ];
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .title(format!("Library: {}", self.dir));
+        f.render_widget(Paragraph::new(lines).block(block), area);
+    }
+
+    fn draw_playlists(&self, f: &mut Frame<'_>, area: Rect) {
+        let rows = self.stats.playlists.iter().map(|playlist| {
+            Row::new(vec![
+                playlist.name.clone(),
+                playlist.tracks.to_string(),
+                format_size(playlist.bytes),
+                format_duration(Duration::from_secs(playlist.seconds)),
+                playlist
+                    .bitrate
+                    .map(|bitrate| format!("~{}k", bitrate))
+                    .unwrap_or_default(),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Min(16),
+                Constraint::Length(7),
+                Constraint::Length(11),
+                Constraint::Length(9),
+                Constraint::Length(7),
+            ],
+        )
+        .header(
+            Row::new(vec!["Playlist", "Tracks", "Size", "Length", "Rate"])
+                .style(self.style("header")),
+        )
+        .block(Block::default().borders(Borders::ALL).title("By playlist"));
+        f.render_widget(table, area);
+    }
+
+    fn draw_bars(&self, f: &mut Frame<'_>, area: Rect, title: &str, bars: Vec<(String, u64)>) {
+        let bars = bars
+            .into_iter()
+            .take(MAX_BARS)
+            .map(|(label, value)| Bar::default().label(label.into()).value(value))
+            .collect::<Vec<_>>();
+        let chart = BarChart::default()
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(title.to_string()),
+            )
+            .data(BarGroup::default().bars(&bars))
+            .bar_width(5)
+            .bar_gap(1)
+            .bar_style(self.style("gauge"))
+            .value_style(self.style("highlight"));
+        f.render_widget(chart, area);
+    }
+
+    fn draw_duplicates(&self, f: &mut Frame<'_>, area: Rect) {
+        let items = self
+            .stats
+            .duplicates
+            .iter()
+            .map(|dup| ListItem::new(format!("{}x {}", dup.playlists, dup.title)))
+            .collect::<Vec<_>>();
+        let list = List::new(items).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title("Most duplicated"),
+        );
+        f.render_widget(list, area);
+    }
+
+    fn draw_history(&self, f: &mut Frame<'_>, area: Rect) {
+        let total: u64 = self.stats.history.iter().sum();
+        let sparkline = Sparkline::default()
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(format!("Downloads, last {} days: {}", HISTORY_DAYS, total)),
+            )
+            .data(&self.stats.history)
+            .style(self.style("gauge"));
+        f.render_widget(sparkline, area);
+    }
+}
+
+impl Component for Dashboard {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.styles = config.styles;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        match action {
+            Action::SelectFolder(dir) => self.dir = dir,
+            Action::EnterDashboard => {
+                self.mode = Mode::Dashboard;
+                return Ok(self.refresh());
+            }
+            Action::Refresh if self.mode == Mode::Dashboard => return Ok(self.refresh()),
+            Action::BackHome => self.mode = Mode::Idle,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        if self.mode != Mode::Dashboard {
+            return Ok(());
+        }
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(4),
+                Constraint::Percentage(45),
+                Constraint::Min(0),
+            ])
+            .split(area);
+        self.draw_summary(f, rows[0]);
+
+        let middle = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+            .split(rows[1]);
+        self.draw_playlists(f, middle[0]);
+        let largest = self
+            .stats
+            .playlists
+            .iter()
+            .map(|playlist| (playlist.name.clone(), playlist.bytes / (1024 * 1024)))
+            .collect();
+        self.draw_bars(f, middle[1], "Largest playlists (MiB)", largest);
+
+        let bottom = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([Constraint::Ratio(1, 4); 4])
+            .split(rows[2]);
+        let formats = self
+            .stats
+            .formats
+            .iter()
+            .map(|(format, count)| (format.clone(), *count))
+            .collect();
+        self.draw_bars(f, bottom[0], "Formats", formats);
+        let bitrates = self
+            .stats
+            .bitrates
+            .iter()
+            .map(|(bitrate, count)| (format!("{}k", bitrate), *count))
+            .collect();
+        self.draw_bars(f, bottom[1], "Bitrates", bitrates);
+        self.draw_duplicates(f, bottom[2]);
+        self.draw_history(f, bottom[3]);
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+use std::path::PathBuf;
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{Component, Frame};
+use crate::{
+    action::Action,
+    config::{Config, SpotifyConfig, DEFAULT_PROFILE},
+    doctor::{self, Check},
+    mode::Mode,
+};
+
+/// Pass/fail report of the environment spotia depends on.
+#[derive(Default)]
+pub struct Diagnostics {
+    mode: Mode,
+    dir: String,
+    /// Name of the active profile, whose credentials are checked.
+    profile: String,
+    spotify: SpotifyConfig,
+    config: Config,
+    checks: Vec<Check>,
+    /// Whether the checks are running in the background.
+    running: bool,
+    command_tx: Option<UnboundedSender<Action>>,
+}
+
+impl Diagnostics {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Idle,
+            profile: DEFAULT_PROFILE.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn style(&self, key: &str) -> Style {
+        self.config.styles.get_style(Mode::Diagnostics, key)
+    }
+
+    /// Runs the checks off the UI thread, as some of them start external programs.
+    fn refresh(&mut self) {
+        let Some(tx) = self.command_tx.clone() else {
+            return;
+        };
+        self.running = true;
+        let spotify = self.spotify.clone();
+        let dir = (!self.dir.is_empty()).then(|| PathBuf::from(&self.dir));
+        tokio::task::spawn_blocking(move || {
+            let checks = doctor::run_checks(&spotify, dir.as_deref());
+            if let Err(err) = tx.send(Action::Diagnosed(checks)) {
+                log::error!("Failed to send action: {}", err);
+            }
+        });
+    }
+}
+
+impl Component for Diagnostics {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        // Resolved the way switching profiles does, so a reload keeps the active profile
+        self.spotify = config
+            .profile(&self.profile)
+            .map(|profile| profile.spotify)
+            .unwrap_or_else(|_| config.config.spotify.clone());
+        self.config = config;
+        Ok(())
+    }
+
+    fn init(&mut self, area: Rect) -> Result<()> {
+        let Some(tx) = self.command_tx.clone() else {
+            return Ok(());
+        };
+        tokio::task::spawn_blocking(move || {
+            let missing = doctor::check_tools()
+                .into_iter()
+                .filter(|check| !check.passed)
+                .map(|check| check.name)
+                .collect::<Vec<_>>();
+            if missing.is_empty() {
+                return;
+            }
+            let error = Action::Error(format!(
+                "Missing required tools: {}\nInstall them and make sure they are in your PATH. \
+                 Open Diagnostics from the menu for details.",
+                missing.join(", ")
+            ));
+            if let Err(err) = tx.send(error) {
+                log::error!("Failed to send action: {}", err);
+            }
+        });
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        match action {
+            Action::SelectFolder(dir) => self.dir = dir,
+            Action::ProfileSwitched(name) => {
+                self.spotify = self.config.profile(&name)?.spotify;
+                self.profile = name;
+            }
+            Action::EnterDiagnostics => {
+                self.mode = Mode::Diagnostics;
+                self.refresh();
+            }
+            Action::Refresh if self.mode == Mode::Diagnostics && !self.running => self.refresh(),
+            Action::Diagnosed(checks) => {
+                self.checks = checks;
+                self.running = false;
+            }
+            Action::BackHome => self.mode = Mode::Idle,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        if self.mode != Mode::Diagnostics {
+            return Ok(());
+        }
+        let rows = self.checks.iter().map(|check| {
+            let status = if check.passed {
+                Cell::from("PASS").style(self.style("pass"))
+            } else {
+                Cell::from("FAIL").style(self.style("fail"))
+            };
+            Row::new(vec![
+                status,
+                Cell::from(check.name.clone()),
+                Cell::from(check.detail.clone()),
+            ])
+        });
+        let failed = self.checks.iter().filter(|check| !check.passed).count();
+        let title = if self.running {
+            "Diagnostics: running checks...".to_string()
+        } else {
+            format!(
+                "Diagnostics: {} passed, {} failed (Refresh to re-run)",
+                self.checks.len() - failed,
+                failed
+            )
+        };
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(6),
+                Constraint::Length(22),
+                Constraint::Min(0),
+            ],
+        )
+        .header(Row::new(vec!["Status", "Check", "Details"]).style(self.style("header")))
+        .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(table, area);
+        Ok(())
+    }
+}
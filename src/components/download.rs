@@ -1,64 +1,308 @@
-use std::time::Instant;
+use std::{
+    collections::VecDeque,
+    fs,
+    path::PathBuf,
+    time::{Instant, SystemTime, UNIX_EPOCH},
+};
 
 use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, MouseEvent, MouseEventKind};
 use ratatui::{prelude::*, widgets::*};
+use tokio::sync::mpsc::UnboundedSender;
 
 use super::Component;
-use crate::{action::Action, mode::Mode, tui::Frame};
+use crate::{
+    action::Action,
+    config::{Config, Styles},
+    mode::Mode,
+    tui::Frame,
+};
+
+/// Maximum number of output lines kept in the scrollback.
+const LOG_CAPACITY: usize = 5000;
+
+/// Lines scrolled per notch of the mouse wheel.
+const WHEEL_LINES: usize = 3;
 
 #[derive(Default)]
 pub struct Download {
     mode: Mode,
-    download_output: String,
+    lines: VecDeque<String>,
+    scroll: usize,
+    height: usize,
+    follow: bool,
+    searching: bool,
+    query: String,
+    /// Jobs done and total jobs of the running batch.
+    progress: Option<(usize, usize)>,
+    data_dir: PathBuf,
+    styles: Styles,
+    command_tx: Option<UnboundedSender<Action>>,
 }
 
 impl Download {
     pub fn new() -> Self {
         Self {
             mode: Mode::Idle,
-            download_output: String::new(),
+            follow: true,
+            ..Default::default()
         }
     }
 
     fn reset(&mut self) {
-        self.download_output.clear();
+        self.lines.clear();
+        self.scroll = 0;
+        self.follow = true;
+        self.searching = false;
+        self.query.clear();
+        self.progress = None;
         self.mode = Mode::Idle;
     }
+
+    fn push_line(&mut self, line: String) {
+        if self.lines.len() == LOG_CAPACITY {
+            self.lines.pop_front();
+            self.scroll = self.scroll.saturating_sub(1);
+        }
+        self.lines.push_back(line);
+    }
+
+    fn max_scroll(&self) -> usize {
+        self.lines.len().saturating_sub(self.height)
+    }
+
+    fn scroll_up(&mut self, n: usize) {
+        self.follow = false;
+        self.scroll = self.scroll.saturating_sub(n);
+    }
+
+    fn scroll_down(&mut self, n: usize) {
+        self.scroll = (self.scroll + n).min(self.max_scroll());
+        if self.scroll == self.max_scroll() {
+            self.follow = true;
+        }
+    }
+
+    /// Scrolls to the next line containing the search query, wrapping around.
+    fn find(&mut self, forward: bool) {
+        if self.query.is_empty() || self.lines.is_empty() {
+            return;
+        }
+        let query = self.query.to_lowercase();
+        let len = self.lines.len();
+        let found = (1..=len)
+            .map(|i| {
+                if forward {
+                    (self.scroll + i) % len
+                } else {
+                    (self.scroll + len - i) % len
+                }
+            })
+            .find(|&i| self.lines[i].to_lowercase().contains(&query));
+        if let Some(i) = found {
+            self.follow = false;
+            self.scroll = i.min(self.max_scroll());
+        }
+    }
+
+    fn save_log(&mut self) -> Result<PathBuf> {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        fs::create_dir_all(&self.data_dir)?;
+        let path = self.data_dir.join(format!("download-{}.log", secs));
+        let content = self.lines.iter().cloned().collect::<Vec<_>>().join("\n");
+        fs::write(&path, content)?;
+        Ok(path)
+    }
+
+    fn highlight<'a>(&self, line: &'a str) -> Line<'a> {
+        if self.query.is_empty() {
+            return Line::from(line);
+        }
+        let lower = line.to_lowercase();
+        let query = self.query.to_lowercase();
+        // Only highlight when lowercasing keeps byte offsets aligned with the original.
+        if lower.len() != line.len() {
+            return Line::from(line);
+        }
+        let mut spans = vec![];
+        let mut last = 0;
+        for (start, _) in lower.match_indices(&query) {
+            spans.push(Span::raw(&line[last..start]));
+            spans.push(Span::styled(
+                &line[start..start + query.len()],
+                self.styles.get_style(Mode::Downloading, "search"),
+            ));
+            last = start + query.len();
+        }
+        spans.push(Span::raw(&line[last..]));
+        Line::from(spans)
+    }
+
+    fn handle_search_key(&mut self, key: KeyEvent) -> Option<Action> {
+        match key.code {
+            KeyCode::Char(c) => {
+                self.query.push(c);
+                None
+            }
+            KeyCode::Backspace => {
+                self.query.pop();
+                None
+            }
+            _ => None,
+        }
+    }
 }
 
 impl Component for Download {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.data_dir = config.config._data_dir;
+        self.styles = config.styles;
+        Ok(())
+    }
+
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
         match action {
-            Action::SelectPlaylist(_) => self.mode = Mode::Downloading,
-            Action::SelectActivePlaylist(_) => self.mode = Mode::Downloading,
+            Action::SelectPlaylists(_) => self.mode = Mode::Downloading,
+            Action::SelectActivePlaylists(_) => self.mode = Mode::Downloading,
+            Action::VerifyPlaylists(_) => self.mode = Mode::Downloading,
+            Action::MirrorPlaylists(_, _) => self.mode = Mode::Downloading,
+            Action::TranscodePlaylists(_) => self.mode = Mode::Downloading,
+            Action::ExportTranscodedPlaylists(_) => self.mode = Mode::Downloading,
+            Action::Progress(done, total) => self.progress = Some((done, total)),
+            Action::ExportPlaylists(_) => self.mode = Mode::Downloading,
+            Action::CreatePlaylist(_, _) => self.mode = Mode::Downloading,
             Action::BackHome => self.reset(),
+            Action::MoveUp if self.mode == Mode::Downloading => self.scroll_up(1),
+            Action::MoveDown if self.mode == Mode::Downloading => self.scroll_down(1),
+            Action::PageUp if self.mode == Mode::Downloading => self.scroll_up(self.height.max(1)),
+            Action::PageDown if self.mode == Mode::Downloading => {
+                self.scroll_down(self.height.max(1))
+            }
+            Action::Top if self.mode == Mode::Downloading => self.scroll_up(self.scroll),
+            Action::Bottom if self.mode == Mode::Downloading => self.scroll_down(self.max_scroll()),
+            Action::Filter if self.mode == Mode::Downloading && !self.searching => {
+                self.searching = true;
+                self.query.clear();
+                return Ok(Some(Action::EnterSearch));
+            }
+            Action::Confirm if self.searching => {
+                self.searching = false;
+                self.find(true);
+                return Ok(Some(Action::QuitSearch));
+            }
+            Action::Cancel if self.searching => {
+                self.searching = false;
+                self.query.clear();
+                return Ok(Some(Action::QuitSearch));
+            }
+            Action::FollowLog if self.mode == Mode::Downloading => self.follow = !self.follow,
+            Action::FindNext if self.mode == Mode::Downloading => self.find(true),
+            Action::FindPrev if self.mode == Mode::Downloading => self.find(false),
+            Action::SaveLog if self.mode == Mode::Downloading => {
+                let msg = match self.save_log() {
+                    Ok(path) => format!("Saved log to {}", path.display()),
+                    Err(err) => format!("Failed to save log: {}", err),
+                };
+                return Ok(Some(Action::Downloading(msg)));
+            }
             Action::Downloading(output) => {
-                self.download_output.push_str(&output);
-                self.download_output.push('\n');
+                for line in output.lines() {
+                    self.push_line(line.to_string());
+                }
             }
             _ => {}
         }
         Ok(None)
     }
 
-    #[allow(clippy::single_match)]
-    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect) -> Result<()> {
-        let row_count = rect.rows().count();
-        let out_count = self.download_output.lines().count();
-        if out_count > row_count {
-            let diff = out_count - row_count;
-            let lines = self.download_output.lines().skip(diff).collect::<Vec<_>>();
-            self.download_output = lines.join("\n");
-        }
-        match self.mode {
-            Mode::Downloading => {
-                let output = Paragraph::new(self.download_output.clone())
-                    .style(Style::default().fg(Color::White).bg(Color::Black))
-                    .block(Block::default().borders(Borders::ALL).title("Output"));
-
-                f.render_widget(output, rect);
+    fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        if self.mode != Mode::Downloading {
+            return Ok(None);
+        }
+        if self.searching {
+            return Ok(self.handle_search_key(key));
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_events(&mut self, mouse: MouseEvent) -> Result<Option<Action>> {
+        if self.mode == Mode::Downloading {
+            match mouse.kind {
+                MouseEventKind::ScrollUp => self.scroll_up(WHEEL_LINES),
+                MouseEventKind::ScrollDown => self.scroll_down(WHEEL_LINES),
+                _ => {}
             }
-            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, rect: Rect) -> Result<()> {
+        if self.mode != Mode::Downloading {
+            return Ok(());
+        }
+        let show_search = self.searching || !self.query.is_empty();
+        let batch = self.progress.filter(|&(_, total)| total > 1);
+        let rects = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(if batch.is_some() { 1 } else { 0 }),
+                Constraint::Min(0),
+                Constraint::Length(if show_search { 1 } else { 0 }),
+            ])
+            .split(rect);
+        let (progress_rect, rects) = (rects[0], &rects[1..]);
+
+        if let Some((done, total)) = batch {
+            let gauge = Gauge::default()
+                .gauge_style(self.styles.get_style(Mode::Downloading, "gauge"))
+                .label(format!("{}/{} playlists", done, total))
+                .ratio(done as f64 / total as f64);
+            f.render_widget(gauge, progress_rect);
+        }
+
+        self.height = rects[0].height.saturating_sub(2) as usize;
+        if self.follow {
+            self.scroll = self.max_scroll();
+        }
+        self.scroll = self.scroll.min(self.max_scroll());
+
+        let lines = self
+            .lines
+            .iter()
+            .skip(self.scroll)
+            .take(self.height)
+            .map(|line| self.highlight(line))
+            .collect::<Vec<_>>();
+        let title = format!(
+            "Output [{}/{}]{}",
+            (self.scroll + self.height).min(self.lines.len()),
+            self.lines.len(),
+            if self.follow { " (following)" } else { "" }
+        );
+        let output = Paragraph::new(lines)
+            .style(self.styles.get_style(Mode::Downloading, "output"))
+            .block(Block::default().borders(Borders::ALL).title(title));
+        f.render_widget(output, rects[0]);
+
+        let mut scrollbar_state = ScrollbarState::new(self.max_scroll()).position(self.scroll);
+        f.render_stateful_widget(
+            Scrollbar::new(ScrollbarOrientation::VerticalRight),
+            rects[0].inner(&Margin {
+                vertical: 1,
+                horizontal: 0,
+            }),
+            &mut scrollbar_state,
+        );
+
+        if show_search {
+            let cursor = if self.searching { "_" } else { "" };
+            let search = Paragraph::new(format!("/{}{}", self.query, cursor));
+            f.render_widget(search, rects[1]);
         }
 
         Ok(())
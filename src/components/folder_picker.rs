@@ -0,0 +1,411 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{centered_rect, help::bindings_for, Component, Frame};
+use crate::{
+    action::Action,
+    config::{Config, Styles},
+    mode::Mode,
+};
+
+/// File created and removed again to check that a folder is writable.
+const WRITE_PROBE: &str = ".spotia-write-test";
+
+/// Browser for picking the music folder, with completion and folder creation.
+#[derive(Default)]
+pub struct FolderPicker {
+    input: String,
+    /// Subfolders of the folder the input points into, narrowed down to the ones
+    /// starting with its last component.
+    subdirs: Vec<String>,
+    index: usize,
+    offset: usize,
+    height: usize,
+    /// Problem with the input, or the outcome of the last folder creation.
+    message: Option<(String, bool)>,
+    /// Folder currently in use, if one was chosen before.
+    current: Option<String>,
+    /// Whether the picker is open, the app starts with it.
+    active: bool,
+    /// First key bound to `NewFolder`, shown when the input does not exist.
+    new_folder_key: Option<String>,
+    styles: Styles,
+}
+
+impl FolderPicker {
+    pub fn new() -> Self {
+        let mut picker = Self {
+            input: home_dir()
+                .map(|home| with_separator(&home.display().to_string()))
+                .unwrap_or_default(),
+            active: true,
+            ..Default::default()
+        };
+        picker.refresh();
+        picker
+    }
+
+    fn set_input(&mut self, input: String) {
+        self.input = input;
+        self.message = None;
+        self.refresh();
+    }
+
+    /// Lists the subfolders matching the input.
+    fn refresh(&mut self) {
+        let (dir, prefix) = split_input(&self.input);
+        self.subdirs = subdirs(&expand_tilde(dir), prefix.starts_with('.'))
+            .into_iter()
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        self.index = 0;
+        self.offset = 0;
+    }
+
+    fn select(&mut self, index: usize) {
+        self.index = index.min(self.subdirs.len().saturating_sub(1));
+        let height = self.height.max(1);
+        if self.index < self.offset {
+            self.offset = self.index;
+        } else if self.index >= self.offset + height {
+            self.offset = self.index + 1 - height;
+        }
+    }
+
+    /// Moves into the highlighted subfolder.
+    fn open(&mut self) {
+        if let Some(name) = self.subdirs.get(self.index) {
+            let (dir, _) = split_input(&self.input);
+            self.set_input(format!("{}{}/", dir, name));
+        }
+    }
+
+    /// Moves up to the parent of the folder the input points into.
+    fn parent(&mut self) {
+        let (dir, _) = split_input(&self.input);
+        let dir = expand_tilde(dir);
+        if let Some(parent) = dir.parent() {
+            self.set_input(with_separator(&parent.display().to_string()));
+        }
+    }
+
+    fn complete(&mut self) {
+        if let Some(input) = complete(&self.input) {
+            self.set_input(input);
+        }
+    }
+
+    fn create(&mut self) {
+        let dir = expand_tilde(&self.input);
+        self.message = Some(match fs::create_dir_all(&dir) {
+            Ok(()) => (format!("Created {}", dir.display()), false),
+            Err(err) => (format!("Cannot create {}: {}", dir.display(), err), true),
+        });
+        self.refresh();
+    }
+
+    fn confirm(&mut self) -> Option<Action> {
+        let dir = expand_tilde(&self.input);
+        match check_writable(&dir) {
+            Ok(()) => Some(Action::SelectFolder(without_separator(
+                &dir.display().to_string(),
+            ))),
+            Err(err) => {
+                let hint = match &self.new_folder_key {
+                    Some(key) if !dir.exists() => format!(", press {} to create it", key),
+                    _ => String::new(),
+                };
+                self.message = Some((err + &hint, true));
+                None
+            }
+        }
+    }
+
+    fn style(&self, key: &str) -> Style {
+        self.styles.get_style(Mode::Folder, key)
+    }
+}
+
+impl Component for FolderPicker {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.new_folder_key = bindings_for(&config.keybindings, Mode::Folder)
+            .remove(&Action::NewFolder.to_string())
+            .and_then(|keys| keys.into_iter().next());
+        self.styles = config.styles;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        match action {
+            Action::SelectFolder(dir) => {
+                self.current = Some(dir);
+                self.active = false;
+            }
+            Action::EnterEditing => {
+                let input = self.current.clone().unwrap_or_else(|| self.input.clone());
+                self.set_input(with_separator(&input));
+                self.active = true;
+            }
+            Action::QuitEditing => self.active = false,
+            Action::Confirm if self.active => return Ok(self.confirm()),
+            // There is nothing to go back to before a folder was chosen
+            Action::Cancel if self.active && self.current.is_some() => {
+                return Ok(Some(Action::QuitEditing))
+            }
+            Action::Complete if self.active => self.complete(),
+            Action::MoveUp if self.active => self.select(self.index.saturating_sub(1)),
+            Action::MoveDown if self.active => self.select(self.index + 1),
+            Action::Open if self.active => self.open(),
+            Action::Parent if self.active => self.parent(),
+            Action::NewFolder if self.active => self.create(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        // Everything but typing goes through the keybindings of the Folder mode
+        let mut input = self.input.clone();
+        match key.code {
+            KeyCode::Char(c) if key.modifiers.difference(KeyModifiers::SHIFT).is_empty() => {
+                input.push(c)
+            }
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            _ => return Ok(None),
+        }
+        self.set_input(input);
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        let area = centered_rect(70, 80, area);
+        f.render_widget(Clear, area);
+        let rects = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(1),
+                Constraint::Min(0),
+            ])
+            .split(area);
+
+        let input = Paragraph::new(format!("{}_", self.input))
+            .style(self.style("input"))
+            .block(
+                Block::default()
+                    .title("Choose your music folder")
+                    .borders(Borders::ALL)
+                    .style(self.style("popup")),
+            );
+        f.render_widget(input, rects[0]);
+
+        let message = match &self.message {
+            Some((message, true)) => Line::styled(message.clone(), self.style("error")),
+            Some((message, false)) => Line::styled(message.clone(), self.style("pass")),
+            None => Line::from(""),
+        };
+        f.render_widget(Paragraph::new(message).style(self.style("popup")), rects[1]);
+
+        self.height = rects[2].height.saturating_sub(2) as usize;
+        self.select(self.index);
+        let items = self
+            .subdirs
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(self.height)
+            .map(|(i, name)| {
+                let style = if i == self.index {
+                    self.style("highlight")
+                } else {
+                    Style::default()
+                };
+                ListItem::new(format!("{}/", name)).style(style)
+            })
+            .collect::<Vec<_>>();
+        let (dir, _) = split_input(&self.input);
+        let list = List::new(items).block(
+            Block::default()
+                .title(format!("Folders in {}", expand_tilde(dir).display()))
+                .borders(Borders::ALL)
+                .style(self.style("popup")),
+        );
+        f.render_widget(list, rects[2]);
+        Ok(())
+    }
+}
+
+fn home_dir() -> Option<PathBuf> {
+    env::var_os("HOME").map(PathBuf::from)
+}
+
+/// `path` without its trailing separators, keeping the root as it is.
+fn without_separator(path: &str) -> String {
+    match path.trim_end_matches('/') {
+        "" if path.starts_with('/') => "/".to_string(),
+        trimmed => trimmed.to_string(),
+    }
+}
+
+fn with_separator(path: &str) -> String {
+    if path.ends_with('/') {
+        path.to_string()
+    } else {
+        format!("{}/", path)
+    }
+}
+
+/// Replaces a leading `~` with the home directory.
+pub fn expand_tilde(input: &str) -> PathBuf {
+    match (input.strip_prefix('~'), home_dir()) {
+        (Some(""), Some(home)) => home,
+        (Some(rest), Some(home)) if rest.starts_with('/') => home.join(&rest[1..]),
+        _ => PathBuf::from(input),
+    }
+}
+
+/// Splits the input into the folder it points into, with its trailing separator,
+/// and the start of a subfolder name.
+fn split_input(input: &str) -> (&str, &str) {
+    match input.rfind('/') {
+        Some(pos) => input.split_at(pos + 1),
+        None => ("", input),
+    }
+}
+
+/// Names of the subfolders of `dir`, sorted, leaving out hidden ones unless asked.
+fn subdirs(dir: &Path, hidden: bool) -> Vec<String> {
+    let dir = if dir.as_os_str().is_empty() {
+        Path::new(".")
+    } else {
+        dir
+    };
+    let mut names = fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter(|entry| entry.path().is_dir())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| hidden || !name.starts_with('.'))
+        .collect::<Vec<_>>();
+    names.sort();
+    names
+}
+
+/// Completes the last component of `input` as far as the subfolders matching it
+/// agree, adding a separator once a single one is left. `None` if nothing matches.
+pub fn complete(input: &str) -> Option<String> {
+    let (dir, prefix) = split_input(input);
+    let matches = subdirs(&expand_tilde(dir), prefix.starts_with('.'))
+        .into_iter()
+        .filter(|name| name.starts_with(prefix))
+        .collect::<Vec<_>>();
+    match matches.as_slice() {
+        [] => None,
+        [only] => Some(format!("{}{}/", dir, only)),
+        [first, rest @ ..] => {
+            let common = rest.iter().fold(first.as_str(), |common, name| {
+                let len = common
+                    .char_indices()
+                    .zip(name.chars())
+                    .take_while(|((_, a), b)| a == b)
+                    .last()
+                    .map_or(0, |((i, a), _)| i + a.len_utf8());
+                &common[..len]
+            });
+            Some(format!("{}{}", dir, common))
+        }
+    }
+}
+
+/// Checks that `dir` is an existing folder files can be written to.
+pub fn check_writable(dir: &Path) -> Result<(), String> {
+    if !dir.exists() {
+        return Err(format!("{} does not exist", dir.display()));
+    }
+    if !dir.is_dir() {
+        return Err(format!("{} is not a folder", dir.display()));
+    }
+    let probe = dir.join(WRITE_PROBE);
+    fs::write(&probe, b"")
+        .and_then(|()| fs::remove_file(&probe))
+        .map_err(|err| format!("{} is not writable: {}", dir.display(), err))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::utils::testing::TestDir;
+
+    #[test]
+    fn test_expand_tilde() {
+        let home = home_dir().unwrap();
+        assert_eq!(expand_tilde("~"), home);
+        assert_eq!(expand_tilde("~/Music"), home.join("Music"));
+        assert_eq!(expand_tilde("~user/Music"), PathBuf::from("~user/Music"));
+        assert_eq!(expand_tilde("/srv/~"), PathBuf::from("/srv/~"));
+    }
+
+    #[test]
+    fn test_without_separator() {
+        assert_eq!(without_separator("/srv/music/"), "/srv/music");
+        assert_eq!(without_separator("/srv/music"), "/srv/music");
+        assert_eq!(without_separator("/"), "/");
+        assert_eq!(without_separator("//"), "/");
+    }
+
+    #[test]
+    fn test_complete() -> std::io::Result<()> {
+        let root = TestDir::new("complete");
+        for dir in ["Music", "Musicals", "Podcasts", ".hidden"] {
+            fs::create_dir_all(root.join(dir))?;
+        }
+        fs::write(root.join("Mustard"), b"")?;
+        let root = root.display().to_string();
+
+        assert_eq!(
+            complete(&format!("{}/M", root)),
+            Some(format!("{}/Music", root))
+        );
+        assert_eq!(
+            complete(&format!("{}/Musica", root)),
+            Some(format!("{}/Musicals/", root))
+        );
+        assert_eq!(
+            complete(&format!("{}/P", root)),
+            Some(format!("{}/Podcasts/", root))
+        );
+        assert_eq!(
+            complete(&format!("{}/.h", root)),
+            Some(format!("{}/.hidden/", root))
+        );
+        assert_eq!(complete(&format!("{}/Video", root)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_writable() -> std::io::Result<()> {
+        let root = TestDir::new("writable");
+        fs::write(root.join("file"), b"")?;
+
+        assert_eq!(check_writable(&root), Ok(()));
+        assert!(!root.join(WRITE_PROBE).exists());
+        assert!(check_writable(&root.join("file"))
+            .unwrap_err()
+            .contains("is not a folder"));
+        assert!(check_writable(&root.join("missing"))
+            .unwrap_err()
+            .contains("does not exist"));
+        Ok(())
+    }
+}
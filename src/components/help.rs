@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{centered_rect, Component, Frame};
+use crate::{
+    action::Action,
+    config::{key_sequence_to_string, Config, KeyBindings, Styles},
+    mode::Mode,
+};
+
+/// Actions shown in the hint bar, in order, when the mode binds them.
+const HINT_ACTIONS: [Action; 19] = [
+    Action::Help,
+    Action::Select,
+    Action::Confirm,
+    Action::Cancel,
+    Action::Back,
+    Action::Filter,
+    Action::ToggleMark,
+    Action::MarkAll,
+    Action::CycleSort,
+    Action::ExportMarked,
+    Action::FindNext,
+    Action::FollowLog,
+    Action::SaveLog,
+    Action::Complete,
+    Action::Open,
+    Action::Parent,
+    Action::NewFolder,
+    Action::Refresh,
+    Action::Quit,
+];
+
+/// Overlay listing the keybindings of the mode it was opened from.
+#[derive(Default)]
+pub struct Help {
+    keybindings: KeyBindings,
+    styles: Styles,
+    mode: Option<Mode>,
+}
+
+impl Help {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn show(&mut self, mode: Mode) {
+        self.mode = Some(mode);
+    }
+
+    pub fn hide(&mut self) {
+        self.mode = None;
+    }
+
+    fn style(&self, mode: Mode, key: &str) -> Style {
+        self.styles.get_style(mode, key)
+    }
+
+    /// One line summary of the most useful keys in `mode`.
+    pub fn hint_line(&self, mode: Mode) -> Line<'static> {
+        Line::styled(hints(&self.keybindings, mode), self.style(mode, "hint"))
+    }
+}
+
+/// The hint bar text of `mode`, with the first key bound to each action in
+/// `HINT_ACTIONS`.
+fn hints(keybindings: &KeyBindings, mode: Mode) -> String {
+    let bindings = bindings_for(keybindings, mode);
+    HINT_ACTIONS
+        .iter()
+        .filter_map(|action| {
+            let keys = bindings.get(&action.to_string())?;
+            Some(format!("{}: {}", keys[0], action))
+        })
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+/// The keys bound in `mode`, grouped by the name of the action they trigger.
+pub fn bindings_for(keybindings: &KeyBindings, mode: Mode) -> BTreeMap<String, Vec<String>> {
+    let mut bindings: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (keys, action) in keybindings.get(&mode).into_iter().flatten() {
+        bindings
+            .entry(action.to_string())
+            .or_default()
+            .push(key_sequence_to_string(keys));
+    }
+    for keys in bindings.values_mut() {
+        // Single characters first so the hint bar shows the shortest key
+        keys.sort_by_key(|key| (key.chars().count(), key.clone()));
+    }
+    bindings
+}
+
+impl Component for Help {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.keybindings = config.keybindings;
+        self.styles = config.styles;
+        Ok(())
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        let Some(mode) = self.mode else {
+            return Ok(());
+        };
+        let rows = bindings_for(&self.keybindings, mode)
+            .into_iter()
+            .map(|(action, keys)| Row::new(vec![keys.join(", "), action]))
+            .collect::<Vec<_>>();
+        let area = centered_rect(60, 70, area);
+        f.render_widget(Clear, area);
+        let table = Table::new(rows, [Constraint::Percentage(40), Constraint::Min(0)])
+            .header(Row::new(vec!["Keys", "Action"]).style(self.style(mode, "header")))
+            .block(
+                Block::default()
+                    .title(format!("Help: {:?}", mode))
+                    .borders(Borders::ALL),
+            );
+        f.render_widget(table, area);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::config::parse_key_sequence;
+
+    #[test]
+    fn test_bindings_for_groups_keys_by_action() {
+        let mut home = HashMap::new();
+        for (key, action) in [
+            ("<Down>", Action::MoveDown),
+            ("<j>", Action::MoveDown),
+            ("<Ctrl-c>", Action::Quit),
+        ] {
+            home.insert(parse_key_sequence(key).unwrap(), action);
+        }
+        let keybindings = KeyBindings(HashMap::from([(Mode::Home, home)]));
+
+        let bindings = bindings_for(&keybindings, Mode::Home);
+        assert_eq!(bindings["MoveDown"], vec!["j", "down"]);
+        assert_eq!(bindings["Quit"], vec!["ctrl-c"]);
+        assert!(bindings_for(&keybindings, Mode::Manager).is_empty());
+    }
+
+    #[test]
+    fn test_hints_follow_the_keybindings() {
+        let mut input = HashMap::new();
+        for (key, action) in [
+            ("<Esc>", Action::Cancel),
+            ("<Enter>", Action::Confirm),
+            ("<Up>", Action::MoveUp),
+        ] {
+            input.insert(parse_key_sequence(key).unwrap(), action);
+        }
+        let keybindings = KeyBindings(HashMap::from([(Mode::Input, input)]));
+
+        assert_eq!(
+            hints(&keybindings, Mode::Input),
+            "enter: Confirm  esc: Cancel"
+        );
+        assert_eq!(hints(&keybindings, Mode::Folder), "");
+    }
+}
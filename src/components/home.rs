@@ -1,27 +1,117 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeSet, HashMap},
     env, fs,
     io::{self, BufRead, BufReader, Write},
     path::Path,
     process::{Command, Stdio},
-    time::Duration,
+    time::{Duration, Instant, SystemTime},
 };
 
 use color_eyre::eyre::Result;
-use crossterm::event::{KeyCode, KeyEvent};
+use crossterm::event::{KeyCode, KeyEvent, MouseEvent, MouseEventKind};
 use ratatui::{prelude::*, widgets::*};
-use rspotify::model::SimplifiedPlaylist;
+use rspotify::{model::SimplifiedPlaylist, prelude::Id};
 use serde::{Deserialize, Serialize};
 use tokio::sync::mpsc::UnboundedSender;
 
-use super::{Component, Frame};
+use super::{centered_rect, spotify::playlist_dir, Component, Frame};
 use crate::{
     action::Action,
     app,
     config::{Config, KeyBindings},
+    fuzzy, mirror,
     mode::Mode,
+    mouse::{list_rows, row_at, Click, ClickTracker},
 };
 
+/// Columns of the playlist table, in display order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Column {
+    Name,
+    Tracks,
+    Owner,
+    Visibility,
+    Local,
+    LastSync,
+}
+
+impl Column {
+    const ALL: [Column; 6] = [
+        Column::Name,
+        Column::Tracks,
+        Column::Owner,
+        Column::Visibility,
+        Column::Local,
+        Column::LastSync,
+    ];
+
+    fn title(self) -> &'static str {
+        match self {
+            Column::Name => "Name",
+            Column::Tracks => "Tracks",
+            Column::Owner => "Owner",
+            Column::Visibility => "Visibility",
+            Column::Local => "Local",
+            Column::LastSync => "Last sync",
+        }
+    }
+
+    fn width(self) -> Constraint {
+        match self {
+            Column::Name => Constraint::Min(20),
+            Column::Tracks => Constraint::Length(7),
+            Column::Owner => Constraint::Length(18),
+            Column::Visibility => Constraint::Length(15),
+            Column::Local => Constraint::Length(6),
+            Column::LastSync => Constraint::Length(10),
+        }
+    }
+}
+
+/// Dialog open over the folder list of the Manager, for the folder at `idx`.
+#[derive(Debug, Clone)]
+enum Prompt {
+    Rename {
+        idx: usize,
+        name: String,
+    },
+    Delete {
+        idx: usize,
+    },
+    Move {
+        idx: usize,
+        roots: Vec<String>,
+        index: usize,
+    },
+    /// Picks the device to mirror the entries at `idxs` to.
+    Mirror {
+        idxs: Vec<usize>,
+        /// Names and paths of the devices.
+        devices: Vec<(String, String)>,
+        index: usize,
+    },
+}
+
+/// What is on disk for a playlist in the music directory.
+#[derive(Debug, Clone, Default)]
+struct LocalStatus {
+    downloaded: bool,
+    last_sync: Option<SystemTime>,
+}
+
+impl LocalStatus {
+    fn scan(dir: &Path) -> Self {
+        let last_sync = fs::metadata(dir.join("save.spotdl"))
+            .or_else(|_| fs::metadata(dir))
+            .and_then(|meta| meta.modified())
+            .ok();
+        Self {
+            downloaded: dir.is_dir(),
+            last_sync,
+        }
+    }
+}
+
 #[derive(Default)]
 pub struct Home {
     mode: Mode,
@@ -31,8 +121,23 @@ pub struct Home {
     playlists: Vec<SimplifiedPlaylist>,
     menus: Vec<String>,
     dirs: Vec<String>,
+    filter: String,
+    filtering: bool,
+    /// Entries matching `filter` as (index into the full list, matched positions).
+    visible: Vec<(usize, Vec<usize>)>,
+    /// Indexes into the full list of the entries marked for a batch operation.
+    marked: BTreeSet<usize>,
+    /// On-disk state of each playlist, in the same order as `playlists`.
+    local: Vec<LocalStatus>,
+    /// Column the playlists are sorted by and whether the order is descending.
+    sort: Option<(Column, bool)>,
+    /// Rows that fit in the list viewport, updated on every draw.
+    height: usize,
+    /// Where the entries of the list were last drawn, for mouse clicks.
+    rows: Rect,
+    clicks: ClickTracker,
+    prompt: Option<Prompt>,
     display_list_len: usize,
-    key_input: String,
     command_tx: Option<UnboundedSender<Action>>,
     config: Config,
 }
@@ -42,52 +147,535 @@ impl Home {
         let menus = vec![
             "Download Playlist".to_string(),
             "Manage Downloads".to_string(),
+            "Import Playlist".to_string(),
+            "Diagnostics".to_string(),
+            "Library Stats".to_string(),
+            "Switch Profile".to_string(),
             "Settings".to_string(),
             "Quit".to_string(),
         ];
         let display_list_len = menus.len();
         Self {
-            mode: Mode::Input,
+            mode: Mode::Folder,
             menus,
             playlists,
             display_list_len,
-            key_input: env::var("HOME").unwrap_or("".to_string()),
             ..Default::default()
         }
     }
     /// Moves the selection cursor up.
     pub fn move_up(&mut self) {
-        if self.index > 0 {
-            self.index -= 1;
-            if self.offset > 0 {
-                self.offset -= 1;
-            }
-        }
+        self.select(self.index.saturating_sub(1));
     }
 
     /// Moves the selection cursor down.
     pub fn move_down(&mut self) {
-        if self.index < self.display_list_len - 1 {
-            self.index += 1;
-            if self.index - self.offset > 5 {
-                self.offset += 1;
-            }
+        self.select(self.index + 1);
+    }
+
+    fn page_up(&mut self) {
+        self.select(self.index.saturating_sub(self.height.max(1)));
+    }
+
+    fn page_down(&mut self) {
+        self.select(self.index + self.height.max(1));
+    }
+
+    /// Moves the cursor to `index`, clamped to the list, and scrolls it into view.
+    fn select(&mut self, index: usize) {
+        self.index = index.min(self.display_list_len.saturating_sub(1));
+        self.scroll_to_selected();
+    }
+
+    fn scroll_to_selected(&mut self) {
+        let height = self.height.max(1);
+        if self.index < self.offset {
+            self.offset = self.index;
+        } else if self.index >= self.offset + height {
+            self.offset = self.index + 1 - height;
         }
     }
 
     pub fn enter_downloader(&mut self) {
-        self.index = 0;
-        self.offset = 0;
         self.mode = Mode::Downloader;
-        self.display_list_len = self.playlists.len();
+        self.refresh_local();
+        self.filter.clear();
+        self.marked.clear();
+        self.apply_filter();
     }
 
     fn enter_manager(&mut self, dirs: Vec<String>) {
-        self.index = 0;
-        self.offset = 0;
         self.dirs = dirs;
         self.mode = Mode::Manager;
-        self.display_list_len = self.dirs.len();
+        self.filter.clear();
+        self.marked.clear();
+        self.apply_filter();
+    }
+
+    /// Narrows the current list down to the entries fuzzy matching the filter.
+    fn apply_filter(&mut self) {
+        self.visible = match self.mode {
+            Mode::Downloader => {
+                fuzzy::filter(&self.filter, self.playlists.iter().map(|p| p.name.as_str()))
+            }
+            Mode::Manager => fuzzy::filter(&self.filter, self.dirs.iter().map(String::as_str)),
+            _ => Vec::new(),
+        };
+        self.sort_visible();
+        self.index = 0;
+        self.offset = 0;
+        self.display_list_len = self.visible.len();
+    }
+
+    fn refresh_local(&mut self) {
+        let dir = Path::new(&self.dir);
+        self.local = self
+            .playlists
+            .iter()
+            .map(|p| LocalStatus::scan(&playlist_dir(dir, &p.name)))
+            .collect();
+    }
+
+    /// Cycles the sort column, going back to the unsorted order after the last one.
+    fn cycle_sort(&mut self) {
+        self.sort = match self.sort {
+            None => Some((Column::ALL[0], false)),
+            Some((column, desc)) => {
+                let pos = Column::ALL.iter().position(|c| *c == column).unwrap_or(0);
+                Column::ALL.get(pos + 1).map(|c| (*c, desc))
+            }
+        };
+        self.apply_filter();
+    }
+
+    fn reverse_sort(&mut self) {
+        if let Some((_, desc)) = &mut self.sort {
+            *desc = !*desc;
+            self.apply_filter();
+        }
+    }
+
+    fn sort_visible(&mut self) {
+        let Some((column, desc)) = self.sort.filter(|_| self.mode == Mode::Downloader) else {
+            return;
+        };
+        let playlists = &self.playlists;
+        let local = &self.local;
+        self.visible.sort_by(|(a, _), (b, _)| {
+            let (pa, pb) = (&playlists[*a], &playlists[*b]);
+            let (la, lb) = (local.get(*a), local.get(*b));
+            let order = match column {
+                Column::Name => pa.name.to_lowercase().cmp(&pb.name.to_lowercase()),
+                Column::Tracks => pa.tracks.total.cmp(&pb.tracks.total),
+                Column::Owner => owner(pa).to_lowercase().cmp(&owner(pb).to_lowercase()),
+                Column::Visibility => visibility(pa).cmp(&visibility(pb)),
+                Column::Local => la.map(|l| l.downloaded).cmp(&lb.map(|l| l.downloaded)),
+                Column::LastSync => la
+                    .and_then(|l| l.last_sync)
+                    .cmp(&lb.and_then(|l| l.last_sync)),
+            };
+            if desc {
+                order.reverse()
+            } else {
+                order
+            }
+        });
+    }
+
+    /// Index into the full playlist or folder list of the selected entry.
+    fn selected(&self) -> Option<usize> {
+        self.visible.get(self.index).map(|(idx, _)| *idx)
+    }
+
+    /// Action for the entry under the cursor in the current mode.
+    fn activate(&self) -> Option<Action> {
+        match self.mode {
+            Mode::Home => match self.index {
+                0 => Some(Action::EnterDownloader),
+                1 => Some(Action::EnterManager),
+                2 => Some(Action::EnterImporter),
+                3 => Some(Action::EnterDiagnostics),
+                4 => Some(Action::EnterDashboard),
+                5 => Some(Action::EnterProfiles),
+                7 => Some(Action::Quit),
+                _ => None,
+            },
+            Mode::Downloader if !self.targets().is_empty() => {
+                Some(Action::SelectPlaylists(self.targets()))
+            }
+            Mode::Manager if !self.targets().is_empty() => {
+                Some(Action::SelectActivePlaylists(self.targets()))
+            }
+            Mode::Waiting => Some(Action::BackHome),
+            _ => None,
+        }
+    }
+
+    /// The marked entries, or the selected one if nothing is marked.
+    fn targets(&self) -> Vec<usize> {
+        if self.marked.is_empty() {
+            self.selected().into_iter().collect()
+        } else {
+            self.marked.iter().copied().collect()
+        }
+    }
+
+    fn toggle_mark(&mut self) {
+        if let Some(idx) = self.selected() {
+            if !self.marked.remove(&idx) {
+                self.marked.insert(idx);
+            }
+            self.move_down();
+        }
+    }
+
+    /// Marks every visible entry, or unmarks them all if they already are.
+    fn mark_all(&mut self) {
+        if self
+            .visible
+            .iter()
+            .all(|(idx, _)| self.marked.contains(idx))
+        {
+            for (idx, _) in &self.visible {
+                self.marked.remove(idx);
+            }
+        } else {
+            self.marked.extend(self.visible.iter().map(|(idx, _)| *idx));
+        }
+    }
+
+    fn invert_marks(&mut self) {
+        for (idx, _) in &self.visible {
+            if !self.marked.remove(idx) {
+                self.marked.insert(*idx);
+            }
+        }
+    }
+
+    /// Opens `prompt` for the selected folder, taking over the keyboard until it
+    /// is closed.
+    fn open_prompt(&mut self, prompt: impl FnOnce(usize) -> Prompt) -> Option<Action> {
+        let idx = self.selected()?;
+        self.prompt = Some(prompt(idx));
+        Some(Action::EnterSearch)
+    }
+
+    fn open_move_prompt(&mut self) -> Option<Action> {
+        let roots = self
+            .config
+            .library_roots()
+            .into_iter()
+            .filter(|root| root.trim_end_matches('/') != self.dir.trim_end_matches('/'))
+            .collect::<Vec<_>>();
+        if roots.is_empty() {
+            return Some(Action::Notify(
+                "No other library root, add some to `library_roots` in the config".to_string(),
+            ));
+        }
+        self.open_prompt(|idx| Prompt::Move {
+            idx,
+            roots,
+            index: 0,
+        })
+    }
+
+    fn open_mirror_prompt(&mut self) -> Option<Action> {
+        let idxs = self.targets();
+        if idxs.is_empty() {
+            return None;
+        }
+        let mut devices = self
+            .config
+            .devices()
+            .into_iter()
+            .map(|(name, device)| (name, device.path))
+            .collect::<Vec<_>>();
+        for mount in mirror::mount_points() {
+            if !devices.iter().any(|(_, path)| *path == mount) {
+                devices.push((String::new(), mount));
+            }
+        }
+        if devices.is_empty() {
+            return Some(Action::Notify(
+                "No device found, plug one in or add one to `devices` in the config".to_string(),
+            ));
+        }
+        self.prompt = Some(Prompt::Mirror {
+            idxs,
+            devices,
+            index: 0,
+        });
+        Some(Action::EnterSearch)
+    }
+
+    /// Typing into the prompt, everything else goes through the keybindings of
+    /// the Input mode.
+    fn handle_prompt_key(&mut self, key: KeyEvent) -> Action {
+        match (&mut self.prompt, key.code) {
+            (Some(Prompt::Rename { name, .. }), KeyCode::Char(c)) => name.push(c),
+            (Some(Prompt::Rename { name, .. }), KeyCode::Backspace) => {
+                name.pop();
+            }
+            // Only an explicit yes deletes, Enter is too easy to press by accident
+            (Some(Prompt::Delete { idx }), KeyCode::Char('y' | 'Y')) => {
+                let action = Action::DeletePlaylist(*idx);
+                return self.close_prompt(Some(action));
+            }
+            (Some(Prompt::Delete { .. }), KeyCode::Char('n' | 'N')) => {
+                return self.close_prompt(None)
+            }
+            _ => {}
+        }
+        Action::Resume
+    }
+
+    /// Answers the prompt with the current choice. A delete has to be confirmed
+    /// with `y` instead.
+    fn confirm_prompt(&mut self) -> Action {
+        let done = match &self.prompt {
+            Some(Prompt::Rename { idx, name }) => Action::RenamePlaylist(*idx, name.clone()),
+            Some(Prompt::Move { idx, roots, index }) => {
+                Action::MovePlaylist(*idx, roots[*index].clone())
+            }
+            Some(Prompt::Mirror {
+                idxs,
+                devices,
+                index,
+            }) => Action::MirrorPlaylists(idxs.clone(), devices[*index].1.clone()),
+            _ => return Action::Resume,
+        };
+        self.close_prompt(Some(done))
+    }
+
+    fn move_in_prompt(&mut self, down: bool) {
+        let (index, len) = match &mut self.prompt {
+            Some(Prompt::Move { index, roots, .. }) => (index, roots.len()),
+            Some(Prompt::Mirror { index, devices, .. }) => (index, devices.len()),
+            _ => return,
+        };
+        *index = if down {
+            (*index + 1).min(len - 1)
+        } else {
+            index.saturating_sub(1)
+        };
+    }
+
+    /// Closes the prompt, sending the action it was answered with if any.
+    fn close_prompt(&mut self, done: Option<Action>) -> Action {
+        self.prompt = None;
+        if let (Some(action), Some(tx)) = (done, &self.command_tx) {
+            if let Err(err) = tx.send(action) {
+                log::error!("Failed to send action: {}", err);
+            }
+        }
+        Action::QuitSearch
+    }
+
+    fn draw_prompt(&self, f: &mut Frame<'_>, area: Rect) {
+        let Some(prompt) = &self.prompt else {
+            return;
+        };
+        let folder = |idx: &usize| self.dirs.get(*idx).cloned().unwrap_or_default();
+        let (title, lines) = match prompt {
+            Prompt::Rename { idx, name } => (
+                format!("Rename {}", folder(idx)),
+                vec![Line::styled(format!("{}_", name), self.style("input"))],
+            ),
+            Prompt::Delete { idx } => (
+                "Delete".to_string(),
+                vec![
+                    Line::from(format!("Delete {}?", folder(idx))),
+                    Line::styled("y: delete  n: keep", self.style("hint")),
+                ],
+            ),
+            Prompt::Move { idx, roots, index } => (
+                format!("Move {} to", folder(idx)),
+                roots
+                    .iter()
+                    .enumerate()
+                    .map(|(i, root)| {
+                        let style = if i == *index {
+                            self.style("highlight")
+                        } else {
+                            Style::default()
+                        };
+                        Line::styled(root.clone(), style)
+                    })
+                    .collect(),
+            ),
+            Prompt::Mirror {
+                idxs,
+                devices,
+                index,
+            } => (
+                format!("Mirror {} playlists to", idxs.len()),
+                devices
+                    .iter()
+                    .enumerate()
+                    .map(|(i, (name, path))| {
+                        let style = if i == *index {
+                            self.style("highlight")
+                        } else {
+                            Style::default()
+                        };
+                        let label = if name.is_empty() {
+                            path.clone()
+                        } else {
+                            format!("{} ({})", name, path)
+                        };
+                        Line::styled(label, style)
+                    })
+                    .collect(),
+            ),
+        };
+        let area = centered_rect(60, 30, area);
+        f.render_widget(Clear, area);
+        let popup = Paragraph::new(lines)
+            .style(self.style("popup"))
+            .block(Block::default().title(title).borders(Borders::ALL));
+        f.render_widget(popup, area);
+    }
+
+    fn handle_filter_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char(c) => self.filter.push(c),
+            KeyCode::Backspace => {
+                self.filter.pop();
+            }
+            _ => return Action::Resume,
+        }
+        self.apply_filter();
+        Action::Resume
+    }
+
+    /// Named style from the config for the current screen.
+    fn style(&self, key: &str) -> Style {
+        self.config.styles.get_style(self.mode, key)
+    }
+
+    /// Label of a list entry with its mark and the characters matching the filter
+    /// highlighted, and the style of its row.
+    fn entry_line(
+        &self,
+        idx: usize,
+        positions: &[usize],
+        label: &str,
+        selected: bool,
+    ) -> (Line<'static>, Style) {
+        let base = if selected {
+            self.style("highlight")
+        } else {
+            Style::default()
+        };
+        let matched = if selected {
+            base.add_modifier(Modifier::BOLD | Modifier::UNDERLINED)
+        } else {
+            self.style("match")
+        };
+        let mark = if self.marked.contains(&idx) {
+            "● "
+        } else {
+            "  "
+        };
+        let spans = std::iter::once(Span::styled(mark, base))
+            .chain(label.chars().enumerate().map(|(pos, c)| {
+                let style = if positions.contains(&pos) {
+                    matched
+                } else {
+                    base
+                };
+                Span::styled(c.to_string(), style)
+            }))
+            .collect::<Vec<_>>();
+        (Line::from(spans), base)
+    }
+
+    /// Border block showing the filter and how many entries are marked.
+    fn list_block(&self, total: usize) -> Block<'static> {
+        let mut block = Block::default().borders(Borders::ALL);
+        if self.filtering || !self.filter.is_empty() {
+            let cursor = if self.filtering { "_" } else { "" };
+            block = block.title(format!(
+                "/{}{} ({} of {})",
+                self.filter,
+                cursor,
+                self.visible.len(),
+                total
+            ));
+        }
+        if !self.marked.is_empty() {
+            block = block.title(
+                block::Title::from(format!("{} marked", self.marked.len()))
+                    .alignment(Alignment::Right),
+            );
+        }
+        block
+    }
+
+    fn draw_dirs(&mut self, f: &mut Frame<'_>, area: Rect) {
+        self.height = area.height.saturating_sub(2) as usize;
+        self.rows = list_rows(area, 0);
+        self.scroll_to_selected();
+        let items: Vec<ListItem> = self
+            .visible
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(self.height)
+            .map(|(i, (idx, positions))| {
+                let (line, style) =
+                    self.entry_line(*idx, positions, &self.dirs[*idx], i == self.index);
+                ListItem::new(line).style(style)
+            })
+            .collect();
+        let list = List::new(items).block(self.list_block(self.dirs.len()));
+        f.render_widget(list, area);
+        self.draw_prompt(f, area);
+    }
+
+    fn draw_playlists(&mut self, f: &mut Frame<'_>, area: Rect) {
+        // Borders and the header row
+        self.height = area.height.saturating_sub(3) as usize;
+        self.rows = list_rows(area, 1);
+        self.scroll_to_selected();
+        let now = SystemTime::now();
+        let rows: Vec<Row> = self
+            .visible
+            .iter()
+            .enumerate()
+            .skip(self.offset)
+            .take(self.height)
+            .map(|(i, (idx, positions))| {
+                let playlist = &self.playlists[*idx];
+                let local = self.local.get(*idx).cloned().unwrap_or_default();
+                let (name, style) =
+                    self.entry_line(*idx, positions, &playlist.name, i == self.index);
+                let last_sync = local
+                    .last_sync
+                    .and_then(|time| now.duration_since(time).ok())
+                    .map(format_age)
+                    .unwrap_or_default();
+                Row::new(vec![
+                    Cell::from(name),
+                    Cell::from(playlist.tracks.total.to_string()),
+                    Cell::from(owner(playlist).to_string()),
+                    Cell::from(visibility(playlist)),
+                    Cell::from(if local.downloaded { "yes" } else { "no" }),
+                    Cell::from(last_sync),
+                ])
+                .style(style)
+            })
+            .collect();
+        let header = Column::ALL.iter().map(|column| match self.sort {
+            Some((sorted, desc)) if sorted == *column => {
+                format!("{} {}", column.title(), if desc { "▼" } else { "▲" })
+            }
+            _ => column.title().to_string(),
+        });
+        let table = Table::new(rows, Column::ALL.map(Column::width))
+            .header(Row::new(header).style(self.style("header")))
+            .block(self.list_block(self.playlists.len()));
+        f.render_widget(table, area);
     }
 
     fn enter_home(&mut self) {
@@ -110,114 +698,152 @@ impl Component for Home {
     }
 
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        let browsing = matches!(self.mode, Mode::Home | Mode::Downloader | Mode::Manager);
+        let listing = matches!(self.mode, Mode::Downloader | Mode::Manager);
         match action {
             Action::Tick => {}
-            Action::MoveUp => self.move_up(),
-            Action::MoveDown => self.move_down(),
-            Action::EnterEditing => self.mode = Mode::Input,
+            Action::Confirm if self.filtering => {
+                self.filtering = false;
+                return Ok(Some(Action::QuitSearch));
+            }
+            Action::Cancel if self.filtering => {
+                self.filtering = false;
+                self.filter.clear();
+                self.apply_filter();
+                return Ok(Some(Action::QuitSearch));
+            }
+            Action::Confirm if self.prompt.is_some() => return Ok(Some(self.confirm_prompt())),
+            Action::Cancel if self.prompt.is_some() => return Ok(Some(self.close_prompt(None))),
+            Action::MoveUp if self.prompt.is_some() => self.move_in_prompt(false),
+            Action::MoveDown if self.prompt.is_some() => self.move_in_prompt(true),
+            Action::MoveUp if browsing => self.move_up(),
+            Action::MoveDown if browsing => self.move_down(),
+            Action::PageUp if browsing => self.page_up(),
+            Action::PageDown if browsing => self.page_down(),
+            Action::Top if browsing => self.select(0),
+            Action::Bottom if browsing => self.select(self.display_list_len.saturating_sub(1)),
+            Action::Select => return Ok(self.activate()),
+            Action::Filter if listing => {
+                self.filtering = true;
+                return Ok(Some(Action::EnterSearch));
+            }
+            Action::ToggleMark if listing => self.toggle_mark(),
+            Action::MarkAll if listing => self.mark_all(),
+            Action::InvertMarks if listing => self.invert_marks(),
+            Action::CycleSort if self.mode == Mode::Downloader => self.cycle_sort(),
+            Action::ReverseSort if self.mode == Mode::Downloader => self.reverse_sort(),
+            Action::ExportMarked if self.mode == Mode::Downloader && !self.targets().is_empty() => {
+                return Ok(Some(Action::ExportPlaylists(self.targets())))
+            }
+            Action::ExportAll if self.mode == Mode::Downloader => {
+                return Ok(Some(Action::ExportPlaylists(
+                    self.visible.iter().map(|(idx, _)| *idx).collect(),
+                )))
+            }
+            Action::Rename if self.mode == Mode::Manager => {
+                let name = self
+                    .selected()
+                    .and_then(|idx| self.dirs.get(idx).cloned())
+                    .unwrap_or_default();
+                return Ok(self.open_prompt(|idx| Prompt::Rename { idx, name }));
+            }
+            Action::Delete if self.mode == Mode::Manager => {
+                return Ok(self.open_prompt(|idx| Prompt::Delete { idx }))
+            }
+            Action::Move if self.mode == Mode::Manager => return Ok(self.open_move_prompt()),
+            Action::Duplicate if self.mode == Mode::Manager => {
+                return Ok(self.selected().map(Action::DuplicatePlaylist))
+            }
+            Action::Mirror if self.mode == Mode::Manager => return Ok(self.open_mirror_prompt()),
+            Action::Transcode if self.mode == Mode::Manager && !self.targets().is_empty() => {
+                return Ok(Some(Action::TranscodePlaylists(self.targets())))
+            }
+            Action::ExportTranscoded
+                if self.mode == Mode::Manager && !self.targets().is_empty() =>
+            {
+                return Ok(Some(Action::ExportTranscodedPlaylists(self.targets())))
+            }
+            Action::Verify if self.mode == Mode::Manager && !self.targets().is_empty() => {
+                return Ok(Some(Action::VerifyPlaylists(self.targets())))
+            }
+            Action::EnterEditing => self.mode = Mode::Folder,
             Action::EnterDownloader => self.enter_downloader(),
-            Action::SelectFolder(_) => self.mode = Mode::Home,
+            Action::SelectFolder(dir) => {
+                self.dir = dir;
+                self.mode = Mode::Home;
+            }
             Action::EnterManager => self.mode = Mode::Manager,
+            Action::EnterImporter => self.mode = Mode::Importer,
+            Action::EnterDiagnostics => self.mode = Mode::Diagnostics,
+            Action::EnterDashboard => self.mode = Mode::Dashboard,
+            Action::EnterProfiles => self.mode = Mode::Profiles,
+            Action::LoadPlaylists(playlists) => {
+                self.playlists = playlists;
+                self.refresh_local();
+                if self.mode == Mode::Downloader {
+                    self.apply_filter();
+                }
+            }
             Action::GetDirs(dirs) => self.enter_manager(dirs),
             Action::DownloadFinished => self.mode = Mode::Waiting,
             Action::BackHome => self.enter_home(),
-            Action::QuitEditing => {
-                self.mode = Mode::Home;
-                self.key_input = env::var("HOME").unwrap_or("".to_string())
-            }
-            Action::SelectPlaylist(_) | Action::SelectActivePlaylist(_) => {
-                self.mode = Mode::Downloading
-            }
+            Action::QuitEditing => self.mode = Mode::Home,
+            Action::SelectPlaylists(_)
+            | Action::SelectActivePlaylists(_)
+            | Action::VerifyPlaylists(_)
+            | Action::MirrorPlaylists(_, _)
+            | Action::TranscodePlaylists(_)
+            | Action::ExportTranscodedPlaylists(_)
+            | Action::Requeue(_)
+            | Action::ExportPlaylists(_) => self.mode = Mode::Downloading,
             _ => {}
         }
         Ok(None)
     }
 
     fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<Action>> {
-        let action = match self.mode {
-            Mode::Input => match key.code {
-                KeyCode::Char(value) => {
-                    self.key_input.push(value);
-                    Action::Resume
-                }
-                KeyCode::Esc => Action::QuitEditing,
-                KeyCode::Backspace => {
-                    self.key_input.pop();
-                    Action::Resume
-                }
-                KeyCode::Enter => {
-                    self.mode = Mode::Home;
-                    Action::SelectFolder(self.key_input.clone())
+        if self.filtering {
+            return Ok(Some(self.handle_filter_key(key)));
+        }
+        if self.prompt.is_some() {
+            return Ok(Some(self.handle_prompt_key(key)));
+        }
+        Ok(None)
+    }
+
+    fn handle_mouse_events(&mut self, mouse: MouseEvent) -> Result<Option<Action>> {
+        let browsing = matches!(self.mode, Mode::Home | Mode::Downloader | Mode::Manager);
+        if !browsing || self.filtering || self.prompt.is_some() {
+            return Ok(None);
+        }
+        match mouse.kind {
+            MouseEventKind::ScrollUp => self.move_up(),
+            MouseEventKind::ScrollDown => self.move_down(),
+            _ => {
+                let Some(index) = row_at(self.rows, self.offset, self.display_list_len, &mouse)
+                else {
+                    return Ok(None);
+                };
+                match self.clicks.click(&mouse, Instant::now()) {
+                    Some(Click::Single) => self.select(index),
+                    Some(Click::Double) => {
+                        self.select(index);
+                        return Ok(self.activate());
+                    }
+                    None => {}
                 }
-                _ => Action::Resume,
-            },
-            Mode::Home => match key.code {
-                KeyCode::Up => Action::MoveUp,
-                KeyCode::Down => Action::MoveDown,
-                KeyCode::Enter => match self.index {
-                    0 => Action::EnterDownloader,
-                    1 => Action::EnterManager,
-                    2 => Action::Resume,
-                    3 => Action::Quit,
-                    _ => Action::Resume,
-                },
-                _ => Action::Resume,
-            },
-            Mode::Downloader => match key.code {
-                KeyCode::Enter => Action::SelectPlaylist(self.index),
-                _ => Action::Resume,
-            },
-            Mode::Manager => match key.code {
-                KeyCode::Enter => Action::SelectActivePlaylist(self.index),
-                _ => Action::Resume,
-            },
-            Mode::Waiting => match key.code {
-                KeyCode::Enter => Action::BackHome,
-                _ => Action::Resume,
-            },
-            _ => Action::Resume,
-        };
-        Ok(Some(action))
+            }
+        }
+        Ok(None)
     }
 
     fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
         match self.mode {
-            Mode::Downloader => {
-                let playlists: Vec<ListItem> = self
-                    .playlists
-                    .iter()
-                    .skip(self.offset)
-                    .enumerate()
-                    .map(|(i, item)| {
-                        let style = if i == self.index - self.offset {
-                            Style::default().bg(Color::Yellow).fg(Color::Black)
-                        } else {
-                            Style::default()
-                        };
-                        ListItem::new(item.name.clone()).style(style)
-                    })
-                    .collect();
-                let playlist_menu = List::new(playlists.clone())
-                    .block(Block::default().borders(Borders::ALL))
-                    .highlight_style(Style::default().bg(Color::Yellow).fg(Color::Black));
-
-                f.render_widget(playlist_menu, area);
-            }
-            Mode::Input => {
-                f.render_widget(Clear, area);
-                let popup_block = Block::default()
-                    .title("Choose your folder")
-                    .borders(Borders::ALL)
-                    .style(Style::default().bg(Color::Gray));
-
-                let popup = Paragraph::new(self.key_input.clone())
-                    .style(Style::default().bg(Color::White).fg(Color::Black))
-                    .block(popup_block);
-
-                let center = centered_rect(60, 25, area);
-                f.render_widget(popup, center);
-            }
+            Mode::Downloader => self.draw_playlists(f, area),
             Mode::Home => {
+                self.height = area.height.saturating_sub(2) as usize;
+                self.rows = list_rows(area, 0);
+                self.scroll_to_selected();
                 let menus: Vec<ListItem> = self
                     .menus
                     .iter()
@@ -225,63 +851,51 @@ impl Component for Home {
                     .enumerate()
                     .map(|(i, item)| {
                         let style = if i == self.index - self.offset {
-                            Style::default().bg(Color::Yellow).fg(Color::Black)
+                            self.style("highlight")
                         } else {
                             Style::default()
                         };
                         ListItem::new(item.clone()).style(style)
                     })
                     .collect();
-                let menu = List::new(menus.clone())
-                    .block(Block::default().borders(Borders::ALL))
-                    .highlight_style(Style::default().bg(Color::Yellow).fg(Color::Black));
+                let menu = List::new(menus.clone()).block(Block::default().borders(Borders::ALL));
 
                 f.render_widget(menu, area)
             }
-            Mode::Manager => {
-                let dirs: Vec<ListItem> = self
-                    .dirs
-                    .iter()
-                    .skip(self.offset)
-                    .enumerate()
-                    .map(|(i, item)| {
-                        let style = if i == self.index - self.offset {
-                            Style::default().bg(Color::Yellow).fg(Color::Black)
-                        } else {
-                            Style::default()
-                        };
-                        ListItem::new(item.clone()).style(style)
-                    })
-                    .collect();
-                let dir_menu = List::new(dirs.clone())
-                    .block(Block::default().borders(Borders::ALL))
-                    .highlight_style(Style::default().bg(Color::Yellow).fg(Color::Black));
-                f.render_widget(dir_menu, area);
-            }
+            Mode::Manager => self.draw_dirs(f, area),
             _ => {}
         }
         Ok(())
     }
 }
 
-fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
-    // Cut the given rectangle into three vertical pieces
-    let popup_layout = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage((100 - percent_y) / 2),
-            Constraint::Percentage(percent_y),
-            Constraint::Percentage((100 - percent_y) / 2),
-        ])
-        .split(r);
-
-    // Then cut the middle vertical piece into three width-wise pieces
-    Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([
-            Constraint::Percentage((100 - percent_x) / 2),
-            Constraint::Percentage(percent_x),
-            Constraint::Percentage((100 - percent_x) / 2),
-        ])
-        .split(popup_layout[1])[1] // Return the middle chunk
+fn owner(playlist: &SimplifiedPlaylist) -> &str {
+    playlist
+        .owner
+        .display_name
+        .as_deref()
+        .unwrap_or_else(|| playlist.owner.id.id())
+}
+
+fn visibility(playlist: &SimplifiedPlaylist) -> String {
+    let access = match playlist.public {
+        Some(true) => "public",
+        Some(false) => "private",
+        None => "unknown",
+    };
+    if playlist.collaborative {
+        format!("{} collab", access)
+    } else {
+        access.to_string()
+    }
+}
+
+/// Short human readable age, e.g. `3d ago`.
+fn format_age(age: Duration) -> String {
+    match age.as_secs() {
+        secs if secs < 60 => "just now".to_string(),
+        secs if secs < 3600 => format!("{}m ago", secs / 60),
+        secs if secs < 86400 => format!("{}h ago", secs / 3600),
+        secs => format!("{}d ago", secs / 86400),
+    }
 }
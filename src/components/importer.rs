@@ -0,0 +1,235 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::{Component, Frame};
+use crate::{
+    action::Action,
+    config::{Config, Styles},
+    import::{is_importable, ImportMatch},
+    mode::Mode,
+};
+
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq)]
+enum Stage {
+    #[default]
+    Files,
+    Matching,
+    Review,
+}
+
+#[derive(Default)]
+pub struct Importer {
+    mode: Mode,
+    stage: Stage,
+    dir: String,
+    files: Vec<PathBuf>,
+    file: Option<PathBuf>,
+    matches: Vec<ImportMatch>,
+    state: TableState,
+    styles: Styles,
+    command_tx: Option<UnboundedSender<Action>>,
+}
+
+impl Importer {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Idle,
+            ..Default::default()
+        }
+    }
+
+    fn enter(&mut self) {
+        self.mode = Mode::Importer;
+        self.stage = Stage::Files;
+        self.files = fs::read_dir(&self.dir)
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.is_file() && is_importable(path))
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.files.sort();
+        self.state.select(Some(0));
+    }
+
+    fn reset(&mut self) {
+        self.mode = Mode::Idle;
+        self.stage = Stage::Files;
+        self.file = None;
+        self.matches.clear();
+    }
+
+    fn len(&self) -> usize {
+        match self.stage {
+            Stage::Files => self.files.len(),
+            Stage::Matching => 0,
+            Stage::Review => self.matches.len(),
+        }
+    }
+
+    fn move_up(&mut self) {
+        let idx = self.state.selected().unwrap_or(0);
+        self.state.select(Some(idx.saturating_sub(1)));
+    }
+
+    fn move_down(&mut self) {
+        let idx = self.state.selected().unwrap_or(0);
+        if idx + 1 < self.len() {
+            self.state.select(Some(idx + 1));
+        }
+    }
+
+    fn playlist_name(&self) -> String {
+        self.file
+            .as_deref()
+            .and_then(Path::file_stem)
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("Imported playlist")
+            .to_string()
+    }
+
+    fn style(&self, key: &str) -> Style {
+        self.styles.get_style(Mode::Importer, key)
+    }
+
+    fn draw_files(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let rows = self.files.iter().map(|path| {
+            let name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .unwrap_or_default();
+            Row::new(vec![name.to_string()])
+        });
+        let title = if self.files.is_empty() {
+            format!("No .m3u or .csv files found in {}", self.dir)
+        } else {
+            "Choose a playlist file to import".to_string()
+        };
+        let table = Table::new(rows, [Constraint::Percentage(100)])
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(self.style("highlight"));
+        f.render_stateful_widget(table, area, &mut self.state);
+    }
+
+    fn draw_review(&mut self, f: &mut Frame<'_>, area: Rect) {
+        let header =
+            Row::new(vec!["", "Entry", "Spotify match", "Confidence"]).style(self.style("header"));
+        let rows = self.matches.iter().map(|m| {
+            let confidence = match m.confidence {
+                80.. => self.style("pass"),
+                50..=79 => self.style("warn"),
+                _ => self.style("fail"),
+            };
+            Row::new(vec![
+                Cell::from(if m.include { "[x]" } else { "[ ]" }),
+                Cell::from(m.entry.clone()),
+                Cell::from(m.track.clone()),
+                Cell::from(format!("{}%", m.confidence)).style(confidence),
+            ])
+        });
+        let included = self.matches.iter().filter(|m| m.include).count();
+        let title = format!(
+            "{}: {}/{} tracks selected (Space to toggle, Enter to create)",
+            self.playlist_name(),
+            included,
+            self.matches.len()
+        );
+        let table = Table::new(
+            rows,
+            [
+                Constraint::Length(3),
+                Constraint::Percentage(45),
+                Constraint::Percentage(45),
+                Constraint::Length(10),
+            ],
+        )
+        .header(header)
+        .block(Block::default().borders(Borders::ALL).title(title))
+        .highlight_style(self.style("highlight"));
+        f.render_stateful_widget(table, area, &mut self.state);
+    }
+
+    fn select(&mut self) -> Option<Action> {
+        let idx = self.state.selected().unwrap_or(0);
+        match self.stage {
+            Stage::Files => self.files.get(idx).cloned().map(|path| {
+                self.stage = Stage::Matching;
+                self.file = Some(path.clone());
+                Action::ImportFile(path.to_string_lossy().to_string())
+            }),
+            Stage::Matching => None,
+            Stage::Review => {
+                let uris = self
+                    .matches
+                    .iter()
+                    .filter(|m| m.include)
+                    .filter_map(|m| m.track_uri.clone())
+                    .collect();
+                Some(Action::CreatePlaylist(self.playlist_name(), uris))
+            }
+        }
+    }
+
+    fn toggle_include(&mut self) {
+        let idx = self.state.selected().unwrap_or(0);
+        if self.stage == Stage::Review {
+            if let Some(m) = self.matches.get_mut(idx) {
+                m.include = !m.include && m.track_uri.is_some();
+            }
+        }
+    }
+}
+
+impl Component for Importer {
+    fn register_action_handler(&mut self, tx: UnboundedSender<Action>) -> Result<()> {
+        self.command_tx = Some(tx);
+        Ok(())
+    }
+
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.styles = config.styles;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        match action {
+            Action::SelectFolder(dir) => self.dir = dir,
+            Action::EnterImporter => self.enter(),
+            Action::ImportMatched(matches) if self.mode == Mode::Importer => {
+                self.matches = matches;
+                self.stage = Stage::Review;
+                self.state.select(Some(0));
+            }
+            Action::MoveUp if self.mode == Mode::Importer => self.move_up(),
+            Action::MoveDown if self.mode == Mode::Importer => self.move_down(),
+            Action::Select if self.mode == Mode::Importer => return Ok(self.select()),
+            Action::ToggleMark if self.mode == Mode::Importer => self.toggle_include(),
+            Action::CreatePlaylist(_, _) | Action::BackHome => self.reset(),
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        if self.mode != Mode::Importer {
+            return Ok(());
+        }
+        match self.stage {
+            Stage::Files => self.draw_files(f, area),
+            Stage::Matching => {
+                let text = Paragraph::new("Matching tracks on Spotify...")
+                    .block(Block::default().borders(Borders::ALL));
+                f.render_widget(text, area);
+            }
+            Stage::Review => self.draw_review(f, area),
+        }
+        Ok(())
+    }
+}
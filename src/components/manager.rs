@@ -3,7 +3,7 @@ use std::{
     io::{BufRead, BufReader, Error, ErrorKind, Read, Write},
     path::{Path, PathBuf},
     process::{Command, Stdio},
-    time::Instant,
+    time::{Duration, Instant},
 };
 use zip::write::{FileOptions, ZipWriter};
 
@@ -12,70 +12,96 @@ use ratatui::{prelude::*, widgets::*};
 use tokio::sync::mpsc::UnboundedSender;
 
 use super::Component;
-use crate::{action::Action, tui::Frame};
+use crate::{
+    action::Action,
+    config::{Config, Device},
+    doctor::format_size,
+    library::{self, Change},
+    mirror,
+    transcode::{self, Format, Transcoder},
+    tui::Frame,
+    verify::{self, Broken},
+};
 
 #[derive(Debug, Clone, Default)]
 pub struct Manager {
     playlists: Vec<PathBuf>,
     command_tx: Option<UnboundedSender<Action>>,
     dir: String,
+    trash_dir: Option<PathBuf>,
+    /// Changes made this session, most recent last, for undo.
+    changes: Vec<Change>,
+    tolerance: Duration,
+    /// Files the last verification found broken, until they are repaired.
+    broken: Vec<Broken>,
+    devices: Vec<(String, Device)>,
+    format: Format,
+    /// Built from the config once it is registered.
+    transcoder: Option<Transcoder>,
+    transcode_dir: Option<PathBuf>,
 }
 
 impl Manager {
     pub fn new() -> Self {
-        Manager::default()
-    }
-
-    fn scan_dir(&mut self) {
-        let dirs = fs::read_dir(self.dir.clone()).expect("Failed to read dir");
-        let mut send_dirs = vec![];
-
-        for dir in dirs {
-            let dir = dir.expect("Failed to get entry");
-            let path = dir.path();
-
-            if path.is_dir() {
-                self.playlists.push(path.clone());
-                send_dirs.push(get_dir_name(path.as_path()).unwrap().to_string());
-            } else if let Some(ext) = path.extension() {
-                if let Some(ext_str) = ext.to_str() {
-                    match ext_str.to_lowercase().as_str() {
-                        "zip" | "rar" | "tar" | "7z" => {
-                            self.playlists.push(path.clone());
-                            send_dirs.push(get_dir_name(path.as_path()).unwrap().to_string());
-                        }
-                        _ => {}
-                    }
-                }
-            }
-        }
-        if let Some(tx) = &self.command_tx {
-            tx.send(Action::GetDirs(send_dirs)).unwrap();
+        Manager {
+            tolerance: verify::DEFAULT_TOLERANCE,
+            ..Default::default()
         }
     }
 
-    fn select_playlist(&mut self, idx: usize) {
+    fn scan_dir(&mut self) -> Result<()> {
+        self.playlists = library::entries(Path::new(&self.dir))?;
+        let send_dirs = self
+            .playlists
+            .iter()
+            .filter_map(|path| get_dir_name(path).map(str::to_string))
+            .collect();
+        self.send_action(Action::GetDirs(send_dirs));
+        Ok(())
+    }
+
+    /// Zips the selected folders and unzips the selected archives, one after the other.
+    fn select_playlists(&mut self, idxs: Vec<usize>) {
         let mut clone = self.clone();
-        let playlist = self.playlists[idx].clone();
-        if playlist.is_dir() {
-            tokio::spawn(async move {
-                if let Err(err) = clone.zip_playlist(idx).await {
-                    clone.send_output(format!("Failed to zip playlist: {}", err));
+        let idxs = idxs
+            .into_iter()
+            .filter(|&idx| idx < self.playlists.len())
+            .collect::<Vec<_>>();
+        // zip and unzip are read line by line until they exit
+        tokio::task::spawn_blocking(move || {
+            let total = idxs.len();
+            let mut failed = 0;
+            for (done, idx) in idxs.into_iter().enumerate() {
+                clone.send_action(Action::Progress(done, total));
+                let result = if clone.playlists[idx].is_dir() {
+                    clone.zip_playlist(idx)
+                } else {
+                    clone.unzip_playlist(idx)
+                };
+                if let Err(err) = result {
+                    failed += 1;
+                    clone.send_action(Action::Error(format!(
+                        "Failed to process {}: {}",
+                        clone.playlists[idx].display(),
+                        err
+                    )));
                 }
-            });
-        } else {
-            tokio::spawn(async move {
-                if let Err(err) = clone.unzip_playlist(idx).await {
-                    clone.send_output(format!("Failed to unzip playlist: {}", err));
-                }
-            });
-        }
+            }
+            clone.send_action(Action::Progress(total, total));
+            clone.send_output(format!(
+                "Finished {} of {} playlists! Press Enter to go back to the menu",
+                total - failed,
+                total
+            ));
+            clone.send_action(Action::DownloadFinished);
+        });
     }
 
-    async fn zip_playlist(&mut self, idx: usize) -> Result<()> {
+    fn zip_playlist(&mut self, idx: usize) -> Result<()> {
         self.send_output("Zipping playlist".to_string());
         let path = self.playlists[idx].clone();
-        let name = get_dir_name(&path).unwrap();
+        let name = get_dir_name(&path)
+            .ok_or_else(|| Error::other(format!("{} has no valid name", path.display())))?;
         let archive = format!("{}.zip", name);
 
         let stdout = Command::new("zip")
@@ -84,7 +110,7 @@ impl Manager {
             .stdout(Stdio::piped())
             .spawn()?
             .stdout
-            .ok_or_else(|| Error::new(ErrorKind::Other, "Failed to execute command"))?;
+            .ok_or_else(|| Error::other("Failed to execute command"))?;
 
         let reader = BufReader::new(stdout);
 
@@ -93,19 +119,16 @@ impl Manager {
             .map_while(|line| line.ok())
             .for_each(|line| self.send_output(line));
 
-        self.send_output("Zipping finished! Press Enter to go back to the menu".to_string());
-
-        if let Some(tx) = &self.command_tx {
-            tx.send(Action::DownloadFinished).unwrap();
-        }
+        self.send_output("Zipping finished!".to_string());
 
         Ok(())
     }
 
-    async fn unzip_playlist(&mut self, idx: usize) -> Result<()> {
+    fn unzip_playlist(&mut self, idx: usize) -> Result<()> {
         self.send_output("Unzipping playlist".to_string());
         let path = self.playlists[idx].clone();
-        let name = get_dir_name(&path).unwrap();
+        let name = get_dir_name(&path)
+            .ok_or_else(|| Error::other(format!("{} has no valid name", path.display())))?;
         let archive = format!("{}.zip", name);
 
         let stdout = Command::new("unzip")
@@ -114,7 +137,7 @@ impl Manager {
             .stdout(Stdio::piped())
             .spawn()?
             .stdout
-            .ok_or_else(|| Error::new(ErrorKind::Other, "Failed to execute command"))?;
+            .ok_or_else(|| Error::other("Failed to execute command"))?;
 
         let reader = BufReader::new(stdout);
 
@@ -128,18 +151,341 @@ impl Manager {
             .arg(archive)
             .spawn()?;
 
-        self.send_output("Unzipping finished! Press Enter to go back to the menu".to_string());
+        self.send_output("Unzipping finished!".to_string());
 
-        if let Some(tx) = &self.command_tx {
-            tx.send(Action::DownloadFinished).unwrap();
+        Ok(())
+    }
+
+    /// Checks the audio files of the selected playlist folders in the background,
+    /// listing the broken ones in the output.
+    fn verify_playlists(&mut self, idxs: Vec<usize>) {
+        let mut clone = self.clone();
+        let dirs = idxs
+            .into_iter()
+            .filter_map(|idx| self.playlists.get(idx).cloned())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+        // Probing every file is blocking work, kept off the async workers
+        tokio::task::spawn_blocking(move || {
+            let total = dirs.len();
+            let mut broken = vec![];
+            let mut unchecked = 0;
+            for (done, dir) in dirs.into_iter().enumerate() {
+                clone.send_action(Action::Progress(done, total));
+                let name = get_dir_name(&dir).unwrap_or_default().to_string();
+                clone.send_output(format!("Verifying {}...", name));
+                match verify::check_playlist(&dir, clone.tolerance) {
+                    Ok(report) => {
+                        for file in &report.broken {
+                            clone.send_output(format!(
+                                "  {}: {}",
+                                get_dir_name(&file.path).unwrap_or_default(),
+                                file.problem
+                            ));
+                        }
+                        for path in &report.unchecked {
+                            clone.send_output(format!(
+                                "  {}: not in save.spotdl, length not checked",
+                                get_dir_name(path).unwrap_or_default()
+                            ));
+                        }
+                        clone.send_output(format!(
+                            "{}: {} broken files, {} unchecked",
+                            name,
+                            report.broken.len(),
+                            report.unchecked.len()
+                        ));
+                        unchecked += report.unchecked.len();
+                        broken.extend(report.broken);
+                    }
+                    Err(err) => clone.send_action(Action::Error(format!(
+                        "Failed to verify {}: {}",
+                        dir.display(),
+                        err
+                    ))),
+                }
+            }
+            clone.send_action(Action::Progress(total, total));
+            if unchecked > 0 {
+                clone.send_output(format!(
+                    "{} files have no Spotify length in save.spotdl and were only checked for being readable",
+                    unchecked
+                ));
+            }
+            if broken.is_empty() {
+                clone.send_output(
+                    "All files are fine! Press Enter to go back to the menu".to_string(),
+                );
+            } else {
+                clone.send_output(format!(
+                    "Found {} broken files! Press r to delete them and download them again, or Enter to go back to the menu",
+                    broken.len()
+                ));
+            }
+            clone.send_action(Action::Verified(broken));
+            clone.send_action(Action::DownloadFinished);
+        });
+    }
+
+    /// Mirrors the selected playlist folders to the device at `path` in the
+    /// background, with the settings configured for it.
+    fn mirror_playlists(&mut self, idxs: Vec<usize>, path: String) {
+        let device = self
+            .devices
+            .iter()
+            .map(|(_, device)| device)
+            .find(|device| device.path == path)
+            .cloned()
+            .unwrap_or(Device {
+                path,
+                ..Default::default()
+            });
+        let options = match device.options() {
+            Ok(options) => options,
+            Err(err) => {
+                self.send_action(Action::Error(format!(
+                    "Invalid settings for {}: {}",
+                    device.path, err
+                )));
+                self.send_action(Action::DownloadFinished);
+                return;
+            }
+        };
+        let dirs = idxs
+            .into_iter()
+            .filter_map(|idx| self.playlists.get(idx).cloned())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+        let Some(transcoder) = self.transcoder() else {
+            return;
+        };
+        let mut clone = self.clone();
+        tokio::task::spawn_blocking(move || {
+            clone.send_output(format!(
+                "Mirroring {} playlists to {}...",
+                dirs.len(),
+                device.path
+            ));
+            let result = mirror::sync(
+                &dirs,
+                Path::new(&device.path),
+                options,
+                &transcoder,
+                &mut |line| clone.send_output(line),
+            );
+            clone.prune_cache();
+            match result {
+                Ok(summary) => clone.send_output(format!(
+                    "Copied {}, kept {}, removed {}, skipped {} and failed {} files, {} on the device! Press Enter to go back to the menu",
+                    summary.copied,
+                    summary.kept,
+                    summary.removed,
+                    summary.skipped,
+                    summary.failed,
+                    format_size(summary.bytes)
+                )),
+                Err(err) => clone.send_action(Action::Error(format!(
+                    "Failed to mirror to {}: {}",
+                    device.path, err
+                ))),
+            }
+            clone.send_action(Action::DownloadFinished);
+        });
+    }
+
+    /// Writes transcoded copies of the selected playlist folders in the
+    /// background, in the configured format, zipped when `zip` is set.
+    fn transcode_playlists(&mut self, idxs: Vec<usize>, zip: bool) {
+        let dirs = idxs
+            .into_iter()
+            .filter_map(|idx| self.playlists.get(idx).cloned())
+            .filter(|path| path.is_dir())
+            .collect::<Vec<_>>();
+        let Some(transcoder) = self.transcoder() else {
+            return;
+        };
+        let format = self.format;
+        let convert = if zip {
+            transcode::export_playlist
+        } else {
+            transcode::convert_playlist
+        };
+        let mut clone = self.clone();
+        // ffmpeg and the copies keep the thread busy for minutes
+        tokio::task::spawn_blocking(move || {
+            let total = dirs.len();
+            let mut failed = 0;
+            for (done, dir) in dirs.into_iter().enumerate() {
+                clone.send_action(Action::Progress(done, total));
+                clone.send_output(format!(
+                    "{} {} to {}...",
+                    if zip { "Exporting" } else { "Transcoding" },
+                    get_dir_name(&dir).unwrap_or_default(),
+                    format
+                ));
+                let out_dir = clone.transcode_dir.clone().unwrap_or_else(|| {
+                    dir.parent()
+                        .unwrap_or(Path::new("."))
+                        .join(transcode::TRANSCODED_DIR)
+                });
+                let result = convert(&dir, &out_dir, format, &transcoder, &mut |line| {
+                    clone.send_output(line)
+                });
+                match result {
+                    Ok((target, 0)) => clone.send_output(format!("Wrote {}", target.display())),
+                    Ok((target, errors)) => {
+                        failed += 1;
+                        clone.send_output(format!(
+                            "Wrote {}, leaving out {} files that failed",
+                            target.display(),
+                            errors
+                        ))
+                    }
+                    Err(err) => {
+                        failed += 1;
+                        clone.send_action(Action::Error(format!(
+                            "Failed to transcode {}: {}",
+                            dir.display(),
+                            err
+                        )))
+                    }
+                }
+            }
+            clone.prune_cache();
+            clone.send_action(Action::Progress(total, total));
+            clone.send_output(format!(
+                "Finished {} of {} playlists! Press Enter to go back to the menu",
+                total - failed,
+                total
+            ));
+            clone.send_action(Action::DownloadFinished);
+        });
+    }
+
+    /// The transcoder of the config, ending the job with an error when there is
+    /// none yet.
+    fn transcoder(&mut self) -> Option<Transcoder> {
+        if self.transcoder.is_none() {
+            self.send_action(Action::Error(
+                "The transcoder is not configured".to_string(),
+            ));
+            self.send_action(Action::DownloadFinished);
         }
+        self.transcoder.clone()
+    }
 
-        Ok(())
+    /// Keeps the transcode cache within its size cap once a job is done with it.
+    fn prune_cache(&mut self) {
+        let Some(transcoder) = &self.transcoder else {
+            return;
+        };
+        match transcoder.prune() {
+            Ok(0) => {}
+            Ok(removed) => self.send_output(format!(
+                "Removed {} old files from the transcode cache",
+                removed
+            )),
+            Err(err) => self.send_output(format!("Failed to prune the transcode cache: {}", err)),
+        }
+    }
+
+    /// Deletes the broken files and hands the tracks they belong to over to be
+    /// downloaded again.
+    fn repair(&mut self) -> Option<Action> {
+        if self.broken.is_empty() {
+            return None;
+        }
+        let mut requeue = vec![];
+        for file in std::mem::take(&mut self.broken) {
+            if let Err(err) = fs::remove_file(&file.path) {
+                self.send_action(Action::Error(format!(
+                    "Failed to delete {}: {}",
+                    file.path.display(),
+                    err
+                )));
+                continue;
+            }
+            self.send_output(format!("Deleted {}", file.path.display()));
+            if file.query.is_some() {
+                requeue.push(file);
+            } else {
+                self.send_output(
+                    "  It is not in save.spotdl, sync the playlist to get it back".to_string(),
+                );
+            }
+        }
+        if requeue.is_empty() {
+            return Some(Action::Notify("No track to download again".to_string()));
+        }
+        Some(Action::Requeue(requeue))
+    }
+
+    /// Applies `edit` to the playlist folder at `idx`, remembering the change so
+    /// it can be undone.
+    fn edit_playlist(
+        &mut self,
+        idx: usize,
+        verb: &str,
+        edit: impl FnOnce(&Path) -> std::io::Result<Change>,
+    ) {
+        let Some(path) = self.playlists.get(idx).cloned() else {
+            return;
+        };
+        if !path.is_dir() {
+            self.send_action(Action::Error(format!(
+                "Cannot {} {}, only playlist folders can be",
+                verb,
+                path.display()
+            )));
+            return;
+        }
+        match edit(&path) {
+            Ok(change) => {
+                self.send_action(Action::Notify(format!("Done: {}", change)));
+                self.changes.push(change);
+            }
+            Err(err) => self.send_action(Action::Error(format!(
+                "Failed to {} {}: {}",
+                verb,
+                path.display(),
+                err
+            ))),
+        }
+        self.rescan();
+    }
+
+    fn undo(&mut self) {
+        let Some(change) = self.changes.pop() else {
+            self.send_action(Action::Notify("Nothing to undo".to_string()));
+            return;
+        };
+        match change.undo() {
+            Ok(()) => self.send_action(Action::Notify(format!("Undone: {}", change))),
+            Err(err) => {
+                self.send_action(Action::Error(format!("Failed to undo {}: {}", change, err)))
+            }
+        }
+        self.rescan();
+    }
+
+    fn rescan(&mut self) {
+        if let Err(err) = self.scan_dir() {
+            self.send_action(Action::Error(format!(
+                "Failed to read {}: {}",
+                self.dir, err
+            )));
+        }
     }
 
     fn send_output(&mut self, out: String) {
+        self.send_action(Action::Downloading(out));
+    }
+
+    fn send_action(&self, action: Action) {
         if let Some(tx) = &self.command_tx {
-            tx.send(Action::Downloading(out)).unwrap();
+            if let Err(err) = tx.send(action) {
+                log::error!("Failed to send action: {}", err);
+            }
         }
     }
 }
@@ -150,12 +496,53 @@ impl Component for Manager {
         Ok(())
     }
 
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.tolerance = config.verify_tolerance();
+        self.devices = config.devices();
+        self.transcoder = Some(config.transcoder());
+        self.format = config.config.transcode;
+        self.transcode_dir = config.config.transcode_dir.map(PathBuf::from);
+        self.trash_dir = config.config.trash_dir.map(PathBuf::from);
+        Ok(())
+    }
+
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
-        #[allow(clippy::single_match)]
         match action {
             Action::SelectFolder(dir) => self.dir = dir,
-            Action::EnterManager => self.scan_dir(),
-            Action::SelectActivePlaylist(idx) => self.select_playlist(idx),
+            Action::EnterManager => {
+                if let Err(err) = self.scan_dir() {
+                    self.send_action(Action::Error(format!(
+                        "Failed to read {}: {}",
+                        self.dir, err
+                    )));
+                    return Ok(Some(Action::BackHome));
+                }
+            }
+            Action::SelectActivePlaylists(idxs) => self.select_playlists(idxs),
+            Action::RenamePlaylist(idx, name) => {
+                self.edit_playlist(idx, "rename", |path| library::rename(path, &name))
+            }
+            Action::DeletePlaylist(idx) => {
+                let trash = self.trash_dir.clone();
+                self.edit_playlist(idx, "delete", |path| {
+                    library::delete(path, trash.as_deref())
+                })
+            }
+            Action::MovePlaylist(idx, root) => {
+                self.edit_playlist(idx, "move", |path| library::move_to(path, Path::new(&root)))
+            }
+            Action::DuplicatePlaylist(idx) => {
+                self.edit_playlist(idx, "duplicate", library::duplicate)
+            }
+            Action::Undo => self.undo(),
+            Action::VerifyPlaylists(idxs) => self.verify_playlists(idxs),
+            Action::MirrorPlaylists(idxs, path) => self.mirror_playlists(idxs, path),
+            Action::TranscodePlaylists(idxs) => self.transcode_playlists(idxs, false),
+            Action::ExportTranscodedPlaylists(idxs) => self.transcode_playlists(idxs, true),
+            Action::Verified(broken) => self.broken = broken,
+            Action::Repair => return Ok(self.repair()),
+            Action::BackHome => self.broken.clear(),
+            Action::Quit => library::empty_session_trash(&self.changes),
             _ => {}
         }
         Ok(None)
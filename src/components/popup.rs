@@ -0,0 +1,100 @@
+use std::collections::VecDeque;
+
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{centered_rect, Component, Frame};
+use crate::{
+    action::Action,
+    config::{Config, Styles},
+    mode::Mode,
+};
+
+/// Dismissable popup showing errors reported through `Action::Error`.
+#[derive(Default)]
+pub struct ErrorPopup {
+    errors: VecDeque<String>,
+    show_details: bool,
+    styles: Styles,
+}
+
+impl ErrorPopup {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn is_open(&self) -> bool {
+        !self.errors.is_empty()
+    }
+}
+
+impl Component for ErrorPopup {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.styles = config.styles;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        if let Action::Error(err) = action {
+            log::error!("{}", err);
+            self.errors.push_back(err);
+        }
+        Ok(None)
+    }
+
+    fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        let action = match key.code {
+            KeyCode::Enter | KeyCode::Esc => {
+                self.errors.pop_front();
+                self.show_details = false;
+                // Only hand control back once every queued error has been seen
+                if self.errors.is_empty() {
+                    Some(Action::DismissError)
+                } else {
+                    None
+                }
+            }
+            KeyCode::Char('d') => {
+                self.show_details = !self.show_details;
+                None
+            }
+            _ => None,
+        };
+        Ok(action)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        let Some(err) = self.errors.front() else {
+            return Ok(());
+        };
+        let summary = err.lines().next().unwrap_or_default();
+        let mut text = vec![Line::from(summary.bold())];
+        if self.show_details {
+            text.push(Line::from(""));
+            text.extend(err.lines().map(Line::from));
+        }
+        text.push(Line::from(""));
+        text.push(Line::styled(
+            "Enter: dismiss   d: toggle details",
+            self.styles.get_style(Mode::Error, "hint"),
+        ));
+
+        let title = if self.errors.len() > 1 {
+            format!("Error (1/{})", self.errors.len())
+        } else {
+            "Error".to_string()
+        };
+        let popup = Paragraph::new(text).wrap(Wrap { trim: false }).block(
+            Block::default()
+                .title(title)
+                .borders(Borders::ALL)
+                .border_style(self.styles.get_style(Mode::Error, "error")),
+        );
+
+        let center = centered_rect(60, 40, area);
+        f.render_widget(Clear, center);
+        f.render_widget(popup, center);
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{Component, Frame};
+use crate::{
+    action::Action,
+    config::{Config, Styles},
+    mode::Mode,
+};
+
+/// List of the configured profiles to switch between.
+#[derive(Default)]
+pub struct Profiles {
+    mode: Mode,
+    current: String,
+    names: Vec<String>,
+    styles: Styles,
+    state: ListState,
+}
+
+impl Profiles {
+    pub fn new() -> Self {
+        Self {
+            mode: Mode::Idle,
+            ..Default::default()
+        }
+    }
+
+    fn enter(&mut self) {
+        self.mode = Mode::Profiles;
+        let idx = self.names.iter().position(|name| *name == self.current);
+        self.state.select(idx.or(Some(0)));
+    }
+
+    fn move_up(&mut self) {
+        let idx = self.state.selected().unwrap_or(0);
+        self.state.select(Some(idx.saturating_sub(1)));
+    }
+
+    fn move_down(&mut self) {
+        let idx = self.state.selected().unwrap_or(0);
+        if idx + 1 < self.names.len() {
+            self.state.select(Some(idx + 1));
+        }
+    }
+}
+
+impl Component for Profiles {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.names = config.profile_names();
+        self.styles = config.styles;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        match action {
+            Action::EnterProfiles => self.enter(),
+            Action::ProfileSwitched(name) => self.current = name,
+            Action::MoveUp if self.mode == Mode::Profiles => self.move_up(),
+            Action::MoveDown if self.mode == Mode::Profiles => self.move_down(),
+            Action::Select if self.mode == Mode::Profiles => {
+                let name = self.state.selected().and_then(|idx| self.names.get(idx));
+                return Ok(name.map(|name| Action::SwitchProfile(name.clone())));
+            }
+            Action::BackHome => self.mode = Mode::Idle,
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        if self.mode != Mode::Profiles {
+            return Ok(());
+        }
+        let items = self
+            .names
+            .iter()
+            .map(|name| {
+                if *name == self.current {
+                    ListItem::new(format!("{} (active)", name))
+                } else {
+                    ListItem::new(name.clone())
+                }
+            })
+            .collect::<Vec<_>>();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Switch profile"),
+            )
+            .highlight_style(self.styles.get_style(Mode::Profiles, "highlight"));
+        f.render_stateful_widget(list, area, &mut self.state);
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use color_eyre::eyre::Result;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{prelude::*, widgets::*};
+
+use super::{centered_rect, Component, Frame};
+use crate::{action::Action, config::SpotifyConfig};
+
+const DEFAULT_REDIRECT_URI: &str = "http://localhost:8888/callback";
+
+const FIELDS: [&str; 3] = ["Client ID", "Client Secret", "Redirect URI"];
+
+/// First-run wizard collecting the Spotify API credentials.
+pub struct Setup {
+    values: [String; 3],
+    focus: usize,
+    status: Option<String>,
+    validating: bool,
+}
+
+impl Default for Setup {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Setup {
+    pub fn new() -> Self {
+        Self {
+            values: [
+                String::new(),
+                String::new(),
+                DEFAULT_REDIRECT_URI.to_string(),
+            ],
+            focus: 0,
+            status: None,
+            validating: false,
+        }
+    }
+
+    /// The credentials as entered, ready to be validated and saved.
+    pub fn spotify_config(&self) -> SpotifyConfig {
+        let [id, secret, redirect_uri] = self.values.clone().map(|v| v.trim().to_string());
+        SpotifyConfig {
+            client_id: Some(id),
+            client_secret: Some(secret),
+            redirect_uri: Some(redirect_uri),
+            ..Default::default()
+        }
+    }
+
+    pub fn set_validating(&mut self) {
+        self.validating = true;
+        self.status = Some("Checking credentials with Spotify...".to_string());
+    }
+
+    pub fn set_error(&mut self, err: String) {
+        self.validating = false;
+        self.status = Some(err);
+    }
+
+    fn submit(&mut self) -> Option<Action> {
+        if let Some(idx) = self.values.iter().position(|v| v.trim().is_empty()) {
+            self.focus = idx;
+            self.status = Some(format!("{} is required", FIELDS[idx]));
+            return None;
+        }
+        let uri = self.values[2].trim();
+        if !uri.starts_with("http://") && !uri.starts_with("https://") {
+            self.focus = 2;
+            self.status = Some("Redirect URI must start with http:// or https://".to_string());
+            return None;
+        }
+        Some(Action::SubmitCredentials)
+    }
+}
+
+impl Component for Setup {
+    fn handle_key_events(&mut self, key: KeyEvent) -> Result<Option<Action>> {
+        if self.validating {
+            return Ok(None);
+        }
+        let action = match key.code {
+            KeyCode::Esc => Some(Action::Quit),
+            KeyCode::Tab | KeyCode::Down => {
+                self.focus = (self.focus + 1) % FIELDS.len();
+                None
+            }
+            KeyCode::BackTab | KeyCode::Up => {
+                self.focus = (self.focus + FIELDS.len() - 1) % FIELDS.len();
+                None
+            }
+            KeyCode::Enter if self.focus + 1 < FIELDS.len() => {
+                self.focus += 1;
+                None
+            }
+            KeyCode::Enter => self.submit(),
+            KeyCode::Backspace => {
+                self.values[self.focus].pop();
+                None
+            }
+            KeyCode::Char(c) => {
+                self.values[self.focus].push(c);
+                None
+            }
+            _ => None,
+        };
+        Ok(action)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        let area = centered_rect(70, 70, area);
+        f.render_widget(Clear, area);
+        let block = Block::default()
+            .title("Spotify setup")
+            .borders(Borders::ALL);
+        let inner = block.inner(area);
+        f.render_widget(block, area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(inner);
+
+        let intro = Paragraph::new(
+            "Create an app at https://developer.spotify.com/dashboard and enter its \
+             credentials. Add the redirect URI to the app settings.",
+        )
+        .wrap(Wrap { trim: true });
+        f.render_widget(intro, rows[0]);
+
+        for (i, label) in FIELDS.iter().enumerate() {
+            let value = if i == 1 {
+                "*".repeat(self.values[i].chars().count())
+            } else {
+                self.values[i].clone()
+            };
+            let style = if i == self.focus {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            let field = Paragraph::new(value).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(*label)
+                    .border_style(style),
+            );
+            f.render_widget(field, rows[i + 1]);
+        }
+
+        let help = "Tab: next field   Enter: continue   Esc: quit";
+        let mut text = vec![Line::from(help.dim())];
+        if let Some(status) = &self.status {
+            let style = if self.validating {
+                Style::default()
+            } else {
+                Style::default().fg(Color::Red)
+            };
+            text.insert(0, Line::styled(status.clone(), style));
+        }
+        f.render_widget(Paragraph::new(text).wrap(Wrap { trim: true }), rows[4]);
+        Ok(())
+    }
+}
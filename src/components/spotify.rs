@@ -2,146 +2,361 @@ use crossterm::style::Stylize;
 use futures::TryStreamExt;
 use futures_util::pin_mut;
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     env, fs,
     io::{self, BufRead, BufReader, Write},
-    path::Path,
+    path::{Path, PathBuf},
     process::{Command, Stdio},
     sync::{Arc, Mutex},
     time::Duration,
 };
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
 use ratatui::{prelude::*, widgets::*};
 use rspotify::{
     model::{
         playlist::{FullPlaylist, PlaylistItem, SimplifiedPlaylist},
-        Country, FullTrack, Market, PlayableItem,
+        Country, FullTrack, Market, PlayableId, PlayableItem, SearchResult, SearchType, TrackId,
     },
     prelude::*,
-    scopes, AuthCodeSpotify, Config, Credentials, OAuth,
+    scopes, AuthCodeSpotify, ClientCredsSpotify, Config, Credentials, OAuth,
 };
 
 use super::Component;
-use crate::{action::Action, tui::Frame};
+use crate::{
+    action::Action,
+    config::{self, Profile, SpotifyConfig},
+    export::{export_path, export_to_file, ExportFormat, TrackRecord},
+    import::{confidence, parse_file, ImportEntry, ImportMatch},
+    tui::Frame,
+    verify::Broken,
+};
 use tokio::sync::mpsc::UnboundedSender;
 
 #[derive(Default, Clone)]
 pub struct Spotify {
     spotify: AuthCodeSpotify,
     pub playlists: Vec<SimplifiedPlaylist>,
+    /// Display name, or id, of the logged-in user.
+    pub user: String,
     dir: String,
     command_tx: Option<UnboundedSender<Action>>,
-    download_output: String,
     config: Config,
+    export_format: ExportFormat,
+    spotdl_args: Vec<String>,
 }
 
 impl Spotify {
-    pub async fn new() -> Self {
+    pub async fn new(profile: &Profile) -> Result<Self> {
         let config = Config {
+            token_cached: true,
+            cache_path: profile.token_cache_path(),
             ..Default::default()
         };
 
-        let creds = Credentials::from_env().unwrap();
-        let oauth =
-            OAuth::from_env(scopes!("playlist-read-private playlist-read-collaborative")).unwrap();
+        let settings = &profile.spotify;
+        let creds = resolve_credentials(settings)?.ok_or_else(|| {
+            eyre!("A Spotify client id and secret must be configured to log in to Spotify")
+        })?;
+        let oauth = resolve_oauth(settings).ok_or_else(|| {
+            eyre!("A Spotify redirect URI must be configured to log in to Spotify")
+        })?;
 
         let spotify = AuthCodeSpotify::with_config(creds.clone(), oauth, config.clone());
-        let url = spotify.get_authorize_url(false).unwrap();
-        spotify.prompt_for_token(&url).await.unwrap();
-        let playlists = get_playlists(&spotify).await;
+        let url = spotify.get_authorize_url(false)?;
+        spotify.prompt_for_token(&url).await?;
+        let playlists = get_playlists(&spotify).await?;
+        let me = spotify.current_user().await?;
+        let user = me.display_name.unwrap_or_else(|| me.id.id().to_string());
 
-        Self {
+        Ok(Self {
             spotify,
             playlists,
+            user,
+            dir: profile.music_dir.clone().unwrap_or_default(),
+            spotdl_args: profile.spotdl_args.clone(),
             ..Default::default()
-        }
+        })
     }
 
-    pub fn get_playlist_url(&self, idx: usize) -> String {
-        self.playlists[idx].id.url()
+    fn get_playlist(&self, idx: usize) -> Result<&SimplifiedPlaylist> {
+        self.playlists
+            .get(idx)
+            .ok_or_else(|| eyre!("No playlist at index {}", idx))
     }
 
-    pub fn get_playlist_name(&self, idx: usize) -> String {
-        self.playlists[idx].name.clone()
+    pub fn get_playlist_url(&self, idx: usize) -> Result<String> {
+        Ok(self.get_playlist(idx)?.id.url())
     }
 
-    pub async fn get_tracks(&self, idx: usize) -> Vec<FullTrack> {
-        let playlist = self.playlists[idx].id.clone();
+    pub fn get_playlist_name(&self, idx: usize) -> Result<String> {
+        Ok(self.get_playlist(idx)?.name.clone())
+    }
+
+    /// Get the playlist entries that are tracks, keeping when and by whom they were added
+    pub async fn get_tracks(&self, idx: usize) -> Result<Vec<PlaylistItem>> {
+        let playlist = self.get_playlist(idx)?.id.clone();
         let stream = self.spotify.playlist_items(playlist, None, None);
         pin_mut!(stream);
         let mut tracks = vec![];
-        while let Some(item) = stream.try_next().await.unwrap() {
-            let track = item.track;
-            if let Some(PlayableItem::Track(track)) = track {
-                tracks.push(track);
+        while let Some(item) = stream.try_next().await? {
+            if let Some(PlayableItem::Track(_)) = item.track {
+                tracks.push(item);
             }
         }
-        tracks
+        Ok(tracks)
     }
 
-    //// Get the playlists and launches the download/sync
-    fn select_playlist(&mut self, idx: usize) -> Result<()> {
-        let url = self.get_playlist_url(idx);
-        let name = self.get_playlist_name(idx).replace(' ', "");
-        let path = self.dir.clone() + "/" + &name;
-        let dir_path = Path::new(&path);
+    //// Write the metadata of the given playlists to a single file
+    pub async fn export_playlists(
+        &self,
+        idxs: &[usize],
+        format: ExportFormat,
+        path: &Path,
+    ) -> Result<usize> {
+        let mut records = vec![];
+        for &idx in idxs {
+            let name = self.get_playlist_name(idx)?;
+            records.extend(
+                self.get_tracks(idx)
+                    .await?
+                    .iter()
+                    .filter_map(|item| TrackRecord::from_item(&name, item)),
+            );
+        }
+        export_to_file(&records, format, path)?;
+        Ok(records.len())
+    }
 
-        let download_output = Arc::new(Mutex::new(self.download_output.clone())); // Use Arc<Mutex<_>> to share across threads
+    fn start_export(&mut self, idxs: Vec<usize>) {
         let mut spotify_clone = self.clone();
-        let url_clone = url.clone();
-        let dir_path_clone = dir_path.to_owned();
-        let cmd_tx = self.command_tx.clone();
-
-        if !dir_path.exists() {
-            if let Err(err) = fs::create_dir_all(dir_path) {
-                download_output
-                    .lock()
-                    .unwrap()
-                    .push_str(&format!("Error creating directory: {}\n", err));
-            } else {
-                download_output
-                    .lock()
-                    .unwrap()
-                    .push_str(&format!("Directory {} created successfully!\n", self.dir));
-
-                // Spawn a blocking task to run download_playlist
-                tokio::spawn(async move {
-                    if let Err(err) = spotify_clone
-                        .download_playlist(url_clone, &dir_path_clone)
-                        .await
-                    {
-                        download_output
-                            .lock()
-                            .unwrap()
-                            .push_str(&format!("Error downloading playlist: {}\n", err));
-                    }
-                });
+        let format = self.export_format;
+        let names = idxs
+            .iter()
+            .filter_map(|&idx| self.get_playlist_name(idx).ok())
+            .collect::<Vec<_>>();
+        let path = export_path(Path::new(&self.dir), &names, format);
+        tokio::spawn(async move {
+            spotify_clone.send_output(format!("Exporting {} playlist(s)...", idxs.len()));
+            match spotify_clone.export_playlists(&idxs, format, &path).await {
+                Ok(count) => spotify_clone.send_output(format!(
+                    "Exported {} tracks to {}",
+                    count,
+                    path.display()
+                )),
+                Err(err) => spotify_clone
+                    .send_action(Action::Error(format!("Error exporting playlists: {}", err))),
+            }
+            spotify_clone
+                .send_output("Export finished! Press Enter to go back to the menu".to_string());
+            spotify_clone.send_action(Action::DownloadFinished);
+        });
+    }
+
+    //// Find the Spotify track that best matches an imported entry
+    async fn match_entry(&self, entry: &ImportEntry) -> Result<ImportMatch> {
+        if let Some(isrc) = &entry.isrc {
+            let result = self
+                .spotify
+                .search(
+                    &format!("isrc:{}", isrc),
+                    SearchType::Track,
+                    None,
+                    None,
+                    Some(1),
+                    None,
+                )
+                .await?;
+            if let SearchResult::Tracks(page) = result {
+                if let Some(track) = page.items.into_iter().next() {
+                    return Ok(ImportMatch {
+                        entry: entry.label(),
+                        track_uri: track.id.as_ref().map(|id| id.uri()),
+                        track: track_label(&track),
+                        confidence: 100,
+                        include: true,
+                    });
+                }
             }
-        } else {
-            tokio::spawn(async move {
-                if let Err(err) = spotify_clone.sync_playlist(&dir_path_clone).await {
-                    download_output
-                        .lock()
-                        .unwrap()
-                        .push_str(&format!("Error downloading playlist: {}\n", err));
+        }
+
+        let result = self
+            .spotify
+            .search(
+                &entry.search_query(),
+                SearchType::Track,
+                None,
+                None,
+                Some(5),
+                None,
+            )
+            .await?;
+        let best = match result {
+            SearchResult::Tracks(page) => page
+                .items
+                .into_iter()
+                .map(|track| {
+                    let artists = track
+                        .artists
+                        .iter()
+                        .map(|artist| artist.name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(" ");
+                    (confidence(entry, &artists, &track.name), track)
+                })
+                .max_by_key(|(score, _)| *score),
+            _ => None,
+        };
+        Ok(match best {
+            Some((score, track)) => ImportMatch {
+                entry: entry.label(),
+                track_uri: track.id.as_ref().map(|id| id.uri()),
+                track: track_label(&track),
+                confidence: score,
+                include: score >= 50,
+            },
+            None => ImportMatch::unmatched(entry),
+        })
+    }
+
+    fn start_import(&mut self, path: String) {
+        let spotify_clone = self.clone();
+        tokio::spawn(async move {
+            let entries = match parse_file(Path::new(&path)) {
+                Ok(entries) => entries,
+                Err(err) => {
+                    spotify_clone.send_action(Action::Error(format!(
+                        "Error reading playlist {}: {}",
+                        path, err
+                    )));
+                    spotify_clone.send_action(Action::EnterImporter);
+                    return;
+                }
+            };
+            let mut matches = vec![];
+            for entry in entries {
+                match spotify_clone.match_entry(&entry).await {
+                    Ok(m) => matches.push(m),
+                    Err(err) => {
+                        log::warn!("Failed to match {}: {}", entry.label(), err);
+                        matches.push(ImportMatch::unmatched(&entry))
+                    }
                 }
-            });
+            }
+            spotify_clone.send_action(Action::ImportMatched(matches));
+        });
+    }
+
+    //// Create a private playlist on the account holding the given tracks
+    pub async fn create_playlist(&self, name: &str, uris: &[String]) -> Result<FullPlaylist> {
+        let user = self.spotify.me().await?;
+        let playlist = self
+            .spotify
+            .user_playlist_create(user.id, name, Some(false), None, None)
+            .await?;
+        let ids = uris
+            .iter()
+            .map(|uri| TrackId::from_uri(uri).map(|id| id.into_static()))
+            .collect::<Result<Vec<_>, _>>()?;
+        for chunk in ids.chunks(100) {
+            self.spotify
+                .playlist_add_items(
+                    playlist.id.clone(),
+                    chunk.iter().map(|id| PlayableId::Track(id.clone())),
+                    None,
+                )
+                .await?;
         }
+        Ok(playlist)
+    }
+
+    fn start_create_playlist(&mut self, name: String, uris: Vec<String>) {
+        let mut spotify_clone = self.clone();
+        tokio::spawn(async move {
+            spotify_clone.send_output(format!("Creating playlist {}...", name));
+            match spotify_clone.create_playlist(&name, &uris).await {
+                Ok(playlist) => spotify_clone.send_output(format!(
+                    "Created {} with {} tracks: {}",
+                    playlist.name,
+                    uris.len(),
+                    playlist.id.url()
+                )),
+                Err(err) => spotify_clone
+                    .send_action(Action::Error(format!("Error creating playlist: {}", err))),
+            }
+            spotify_clone
+                .send_output("Import finished! Press Enter to go back to the menu".to_string());
+            spotify_clone.send_action(Action::DownloadFinished);
+        });
+    }
+
+    //// Get the playlists and launches the download/sync of each one in turn
+    fn select_playlists(&mut self, idxs: Vec<usize>) -> Result<()> {
+        let jobs = idxs
+            .iter()
+            .map(|&idx| {
+                let url = self.get_playlist_url(idx)?;
+                let name = self.get_playlist_name(idx)?;
+                let dir_path = playlist_dir(Path::new(&self.dir), &name);
+                Ok((name, url, dir_path))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let mut spotify_clone = self.clone();
+
+        // spotdl runs for minutes and its output is read line by line
+        tokio::task::spawn_blocking(move || {
+            let total = jobs.len();
+            let mut failed = 0;
+            for (done, (name, url, dir_path)) in jobs.into_iter().enumerate() {
+                spotify_clone.send_action(Action::Progress(done, total));
+                if total > 1 {
+                    spotify_clone.send_output(format!("[{}/{}] {}", done + 1, total, name));
+                }
+                if let Err(err) = spotify_clone.fetch_playlist(url, &dir_path) {
+                    failed += 1;
+                    spotify_clone.send_action(Action::Error(format!(
+                        "Error downloading playlist {}: {}",
+                        name, err
+                    )));
+                }
+            }
+            spotify_clone.send_action(Action::Progress(total, total));
+            spotify_clone.send_output(format!(
+                "Finished {} of {} playlists! Press Enter to go back to the menu",
+                total - failed,
+                total
+            ));
+            spotify_clone.send_action(Action::DownloadFinished);
+        });
 
         Ok(())
     }
 
+    /// Syncs the playlist if it was downloaded before, downloads it otherwise.
+    fn fetch_playlist(&mut self, url: String, dir_path: &Path) -> Result<()> {
+        if dir_path.exists() {
+            return self.sync_playlist(dir_path);
+        }
+        fs::create_dir_all(dir_path)
+            .map_err(|err| eyre!("Error creating directory {}: {}", dir_path.display(), err))?;
+        self.send_output(format!(
+            "Directory {} created successfully!",
+            dir_path.display()
+        ));
+        self.download_playlist(url, dir_path)
+    }
+
     //// Sync the selected playlist
-    async fn sync_playlist(&mut self, dir: &Path) -> Result<()> {
+    fn sync_playlist(&mut self, dir: &Path) -> Result<()> {
         self.send_output("Syncing playlist...".to_string());
         let stdout = Command::new("spotdl")
             .args(["sync".to_string(), "save.spotdl".to_string()])
+            .args(&self.spotdl_args)
             .current_dir(dir)
             .stdout(Stdio::piped()) // Redirect stdout to a pipe
             .spawn()?
             .stdout
-            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Failed to execute command"))?;
+            .ok_or_else(|| io::Error::other("Failed to execute command"))?;
 
         let reader = BufReader::new(stdout);
 
@@ -150,17 +365,13 @@ impl Spotify {
             .map_while(|line| line.ok())
             .for_each(|line| self.send_output(line));
 
-        self.send_output("Syncing finished! Press Enter to go back to the menu".to_string());
-
-        if let Some(tx) = &self.command_tx {
-            tx.send(Action::DownloadFinished).unwrap();
-        }
+        self.send_output("Syncing finished!".to_string());
 
         Ok(())
     }
 
     //// Download the selected playlist
-    async fn download_playlist(&mut self, url: String, dir: &Path) -> Result<()> {
+    fn download_playlist(&mut self, url: String, dir: &Path) -> Result<()> {
         self.send_output("Download started...".to_string());
         let stdout = Command::new("spotdl")
             .args([
@@ -170,11 +381,12 @@ impl Spotify {
                 "save.spotdl".to_string(),
                 "--simple-tui".to_string(),
             ])
+            .args(&self.spotdl_args)
             .current_dir(dir)
             .stdout(Stdio::piped()) // Redirect stdout to a pipe
             .spawn()?
             .stdout
-            .ok_or_else(|| io::Error::new(io::ErrorKind::Other, "Failed to execute command"))?;
+            .ok_or_else(|| io::Error::other("Failed to execute command"))?;
 
         let reader = BufReader::new(stdout);
 
@@ -182,18 +394,82 @@ impl Spotify {
             .lines()
             .map_while(|line| line.ok())
             .for_each(|line| self.send_output(line));
-        self.send_output("Download finished! Press Enter to go back to the menu".to_string());
+        self.send_output("Download finished!".to_string());
 
-        if let Some(tx) = &self.command_tx {
-            tx.send(Action::DownloadFinished).unwrap();
+        Ok(())
+    }
+
+    /// Downloads the tracks of deleted broken files again, one playlist folder
+    /// after the other.
+    fn requeue(&mut self, files: Vec<Broken>) {
+        let mut jobs: BTreeMap<PathBuf, Vec<String>> = BTreeMap::new();
+        for file in files {
+            if let (Some(dir), Some(query)) = (file.path.parent(), file.query) {
+                jobs.entry(dir.to_path_buf()).or_default().push(query);
+            }
         }
+        let mut spotify_clone = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let total = jobs.len();
+            let mut failed = 0;
+            for (done, (dir, queries)) in jobs.into_iter().enumerate() {
+                spotify_clone.send_action(Action::Progress(done, total));
+                if let Err(err) = spotify_clone.redownload_tracks(queries, &dir) {
+                    failed += 1;
+                    spotify_clone.send_action(Action::Error(format!(
+                        "Error downloading tracks into {}: {}",
+                        dir.display(),
+                        err
+                    )));
+                }
+            }
+            spotify_clone.send_action(Action::Progress(total, total));
+            spotify_clone.send_output(format!(
+                "Finished {} of {} playlists! Press Enter to go back to the menu",
+                total - failed,
+                total
+            ));
+            spotify_clone.send_action(Action::DownloadFinished);
+        });
+    }
+
+    //// Download the given tracks into a playlist folder
+    fn redownload_tracks(&mut self, queries: Vec<String>, dir: &Path) -> Result<()> {
+        self.send_output(format!(
+            "Downloading {} tracks again into {}...",
+            queries.len(),
+            dir.display()
+        ));
+        let stdout = Command::new("spotdl")
+            .arg("download")
+            .args(queries)
+            .args(&self.spotdl_args)
+            .current_dir(dir)
+            .stdout(Stdio::piped())
+            .spawn()?
+            .stdout
+            .ok_or_else(|| io::Error::other("Failed to execute command"))?;
+
+        let reader = BufReader::new(stdout);
+
+        reader
+            .lines()
+            .map_while(|line| line.ok())
+            .for_each(|line| self.send_output(line));
+        self.send_output("Download finished!".to_string());
 
         Ok(())
     }
 
     fn send_output(&mut self, out: String) {
+        self.send_action(Action::Downloading(out));
+    }
+
+    fn send_action(&self, action: Action) {
         if let Some(tx) = &self.command_tx {
-            tx.send(Action::Downloading(out)).unwrap();
+            if let Err(err) = tx.send(action) {
+                log::error!("Failed to send action: {}", err);
+            }
         }
     }
 }
@@ -203,10 +479,20 @@ impl Component for Spotify {
         self.command_tx = Some(tx);
         Ok(())
     }
+
+    fn register_config_handler(&mut self, config: config::Config) -> Result<()> {
+        self.export_format = config.config.export_format;
+        Ok(())
+    }
+
     fn update(&mut self, action: Action) -> Result<Option<Action>> {
         #[allow(clippy::single_match)]
         match action {
-            Action::SelectPlaylist(idx) => self.select_playlist(idx)?,
+            Action::SelectPlaylists(idxs) => self.select_playlists(idxs)?,
+            Action::ExportPlaylists(idxs) => self.start_export(idxs),
+            Action::ImportFile(path) => self.start_import(path),
+            Action::CreatePlaylist(name, uris) => self.start_create_playlist(name, uris),
+            Action::Requeue(files) => self.requeue(files),
             Action::SelectFolder(dir) => self.dir = dir,
             _ => {}
         }
@@ -218,12 +504,55 @@ impl Component for Spotify {
     }
 }
 
-async fn get_playlists(spotify: &AuthCodeSpotify) -> Vec<SimplifiedPlaylist> {
+/// Folder a playlist is downloaded to inside the music directory.
+pub fn playlist_dir(music_dir: &Path, name: &str) -> PathBuf {
+    music_dir.join(name.replace(' ', ""))
+}
+
+/// Builds the client credentials from the config, falling back to the environment.
+pub fn resolve_credentials(settings: &SpotifyConfig) -> Result<Option<Credentials>> {
+    match (&settings.client_id, settings.client_secret()?) {
+        (Some(id), Some(secret)) => Ok(Some(Credentials::new(id, &secret))),
+        _ => Ok(Credentials::from_env()),
+    }
+}
+
+/// Builds the OAuth settings from the config, falling back to the environment.
+pub fn resolve_oauth(settings: &SpotifyConfig) -> Option<OAuth> {
+    let scopes =
+        scopes!("playlist-read-private playlist-read-collaborative playlist-modify-private");
+    match &settings.redirect_uri {
+        Some(redirect_uri) => Some(OAuth {
+            redirect_uri: redirect_uri.clone(),
+            scopes,
+            ..Default::default()
+        }),
+        None => OAuth::from_env(scopes),
+    }
+}
+
+/// Checks the client id and secret against the Spotify token endpoint.
+pub async fn validate_credentials(creds: Credentials) -> Result<()> {
+    ClientCredsSpotify::new(creds).request_token().await?;
+    Ok(())
+}
+
+fn track_label(track: &FullTrack) -> String {
+    let artists = track
+        .artists
+        .iter()
+        .map(|artist| artist.name.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!("{} - {}", artists, track.name)
+}
+
+async fn get_playlists(spotify: &AuthCodeSpotify) -> Result<Vec<SimplifiedPlaylist>> {
     let stream = spotify.current_user_playlists();
     let mut playlists = vec![];
     pin_mut!(stream);
-    while let Some(item) = stream.try_next().await.unwrap() {
+    while let Some(item) = stream.try_next().await? {
         playlists.push(item);
     }
-    playlists
+    Ok(playlists)
 }
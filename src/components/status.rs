@@ -0,0 +1,136 @@
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use color_eyre::eyre::Result;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{Component, Frame};
+use crate::{
+    action::Action,
+    config::{Config, Styles},
+    doctor::{format_size, free_space},
+    mode::Mode,
+};
+
+/// How long a notification stays in the status bar.
+const NOTICE_DURATION: Duration = Duration::from_secs(3);
+
+/// Bottom bar summarising the mode, music directory, account and running jobs.
+#[derive(Default)]
+pub struct StatusBar {
+    mode: Mode,
+    dir: String,
+    user: String,
+    running: usize,
+    queued: usize,
+    free: Option<u64>,
+    notice: Option<(String, Instant)>,
+    styles: Styles,
+}
+
+impl StatusBar {
+    pub fn new(user: String) -> Self {
+        Self {
+            user,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = mode;
+    }
+
+    fn refresh_free_space(&mut self) {
+        self.free = (!self.dir.is_empty())
+            .then(|| free_space(Path::new(&self.dir)))
+            .flatten();
+    }
+}
+
+impl Component for StatusBar {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.styles = config.styles;
+        Ok(())
+    }
+
+    fn update(&mut self, action: Action) -> Result<Option<Action>> {
+        match action {
+            Action::SelectFolder(dir) => {
+                self.dir = dir;
+                self.refresh_free_space();
+            }
+            Action::LoggedIn(user) => self.user = user,
+            Action::Notify(message) => self.notice = Some((message, Instant::now())),
+            Action::Progress(done, total) => {
+                self.running = usize::from(done < total);
+                self.queued = total.saturating_sub(done + 1);
+            }
+            Action::SelectPlaylists(_)
+            | Action::SelectActivePlaylists(_)
+            | Action::VerifyPlaylists(_)
+            | Action::MirrorPlaylists(_, _)
+            | Action::TranscodePlaylists(_)
+            | Action::ExportTranscodedPlaylists(_)
+            | Action::Requeue(_)
+            | Action::ExportPlaylists(_)
+            | Action::ImportFile(_)
+            | Action::CreatePlaylist(_, _) => self.running = 1,
+            // Matching an import ends with its review, or back in the importer on error
+            Action::ImportMatched(_) | Action::EnterImporter | Action::BackHome => {
+                self.running = 0;
+                self.queued = 0;
+            }
+            Action::DownloadFinished => {
+                self.running = 0;
+                self.queued = 0;
+                self.refresh_free_space();
+            }
+            _ => {}
+        }
+        Ok(None)
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        let dir = if self.dir.is_empty() {
+            "no folder"
+        } else {
+            &self.dir
+        };
+        let user = if self.user.is_empty() {
+            "not logged in"
+        } else {
+            &self.user
+        };
+        let free = self
+            .free
+            .map(|bytes| format!("{} free", format_size(bytes)))
+            .unwrap_or_default();
+        let mode = Span::styled(
+            format!(" {:?} ", self.mode),
+            self.styles.get_style(self.mode, "status_mode"),
+        );
+        self.notice = self
+            .notice
+            .take()
+            .filter(|(_, shown)| shown.elapsed() < NOTICE_DURATION);
+        let left = match &self.notice {
+            Some((message, _)) => Line::from(vec![mode, Span::raw(format!(" {}", message))]),
+            None => Line::from(vec![
+                mode,
+                Span::raw(format!(" {}  ", dir)),
+                Span::raw(format!("user: {}", user)),
+            ]),
+        };
+        let right = Line::from(format!(
+            "jobs: {} running, {} queued  {} ",
+            self.running, self.queued, free
+        ))
+        .alignment(Alignment::Right);
+        let style = self.styles.get_style(self.mode, "status");
+        f.render_widget(Paragraph::new(left).style(style), area);
+        f.render_widget(Paragraph::new(right).style(style), area);
+        Ok(())
+    }
+}
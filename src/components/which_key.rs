@@ -0,0 +1,68 @@
+use color_eyre::eyre::Result;
+use crossterm::event::KeyEvent;
+use ratatui::{prelude::*, widgets::*};
+
+use super::{Component, Frame};
+use crate::{
+    action::Action,
+    config::{key_sequence_to_string, Config, Styles},
+    mode::Mode,
+};
+
+/// Popup in the bottom right corner listing the keys that can complete the
+/// chord in progress.
+#[derive(Default)]
+pub struct WhichKey {
+    mode: Mode,
+    pending: Vec<KeyEvent>,
+    continuations: Vec<(Vec<KeyEvent>, Action)>,
+    styles: Styles,
+}
+
+impl WhichKey {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn show(
+        &mut self,
+        mode: Mode,
+        pending: &[KeyEvent],
+        continuations: Vec<(Vec<KeyEvent>, Action)>,
+    ) {
+        self.mode = mode;
+        self.pending = pending.to_vec();
+        self.continuations = continuations;
+    }
+}
+
+impl Component for WhichKey {
+    fn register_config_handler(&mut self, config: Config) -> Result<()> {
+        self.styles = config.styles;
+        Ok(())
+    }
+
+    fn draw(&mut self, f: &mut Frame<'_>, area: Rect) -> Result<()> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let rows = self
+            .continuations
+            .iter()
+            .map(|(keys, action)| Row::new(vec![key_sequence_to_string(keys), action.to_string()]))
+            .collect::<Vec<_>>();
+        let width = 40.min(area.width);
+        let height = (rows.len() as u16 + 2).min(area.height);
+        let area = Rect::new(area.right() - width, area.bottom() - height, width, height);
+        f.render_widget(Clear, area);
+        let table = Table::new(rows, [Constraint::Length(12), Constraint::Min(0)])
+            .style(self.styles.get_style(self.mode, "popup"))
+            .block(
+                Block::default()
+                    .title(format!("{} …", key_sequence_to_string(&self.pending)))
+                    .borders(Borders::ALL),
+            );
+        f.render_widget(table, area);
+        Ok(())
+    }
+}
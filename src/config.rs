@@ -1,26 +1,196 @@
-use std::{collections::HashMap, fmt, path::PathBuf};
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    path::{Path, PathBuf},
+    process::Command,
+    time::{Duration, SystemTime},
+};
 
-use color_eyre::eyre::Result;
+use color_eyre::eyre::{eyre, Result};
 use config::Value;
 use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
 use derive_deref::{Deref, DerefMut};
 use ratatui::style::{Color, Modifier, Style};
 use serde::{
-    de::{self, Deserializer, MapAccess, Visitor},
+    de::{self, Deserializer, IntoDeserializer, MapAccess, Visitor},
     Deserialize, Serialize,
 };
 use serde_json::Value as JsonValue;
 
-use crate::{action::Action, mode::Mode};
+use crate::{
+    action::Action,
+    export::ExportFormat,
+    mirror::{self, Compare},
+    mode::Mode,
+    transcode::{self, Codec, Format, Transcoder},
+};
 
 const CONFIG: &str = include_str!("../.config/config.json5");
 
+/// User config files, in the order they are merged.
+const CONFIG_FILES: [(&str, config::FileFormat); 5] = [
+    ("config.json5", config::FileFormat::Json5),
+    ("config.json", config::FileFormat::Json),
+    ("config.yaml", config::FileFormat::Yaml),
+    ("config.toml", config::FileFormat::Toml),
+    ("config.ini", config::FileFormat::Ini),
+];
+
+/// Themes shipped with spotia besides `default`, which is the `styles` of the
+/// default config.
+const THEMES: [(&str, &str); 3] = [
+    ("light", include_str!("../.config/themes/light.json5")),
+    (
+        "high-contrast",
+        include_str!("../.config/themes/high-contrast.json5"),
+    ),
+    (
+        "monochrome",
+        include_str!("../.config/themes/monochrome.json5"),
+    ),
+];
+
+/// File in the config directory the setup wizard writes credentials to.
+pub const CREDENTIALS_FILE: &str = "credentials.json5";
+
 #[derive(Clone, Debug, Deserialize, Default)]
 pub struct AppConfig {
     #[serde(default)]
     pub _data_dir: PathBuf,
     #[serde(default)]
     pub _config_dir: PathBuf,
+    #[serde(default)]
+    pub export_format: ExportFormat,
+    #[serde(default)]
+    pub spotify: SpotifyConfig,
+    #[serde(default)]
+    pub profiles: HashMap<String, Profile>,
+    /// Name of a built-in theme the user's own `styles` are layered on.
+    #[serde(default)]
+    pub theme: Option<String>,
+    /// How long a multi-key binding waits for its next key, in milliseconds.
+    #[serde(default)]
+    pub chord_timeout_ms: Option<u64>,
+    /// Whether lists and logs can be clicked and scrolled with the mouse.
+    #[serde(default)]
+    pub mouse: bool,
+    /// Other music folders playlists can be moved to from the Manager.
+    #[serde(default)]
+    pub library_roots: Vec<String>,
+    /// Folder deleted playlists are kept in. Without one they are only kept
+    /// until the app quits.
+    #[serde(default)]
+    pub trash_dir: Option<String>,
+    /// How many seconds a verified file may be longer or shorter than its track.
+    #[serde(default)]
+    pub verify_tolerance_secs: Option<u64>,
+    /// Phones, USB sticks or folders playlists can be mirrored to, by name.
+    #[serde(default)]
+    pub devices: HashMap<String, Device>,
+    /// Format playlist folders are converted to from the Manager.
+    #[serde(default)]
+    pub transcode: Format,
+    /// How many files are transcoded at once, one per CPU by default.
+    #[serde(default)]
+    pub transcode_jobs: Option<usize>,
+    /// Folder transcoded playlists are written to, by default a hidden
+    /// `.spotia-transcoded` folder in their library root.
+    #[serde(default)]
+    pub transcode_dir: Option<String>,
+    /// Most space the transcode cache may take up, in MiB.
+    #[serde(default)]
+    pub transcode_cache_mb: Option<u64>,
+}
+
+/// Name of the profile used when `--profile` is not given.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// A named account with its own credentials, music directory and spotdl options.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Profile {
+    #[serde(skip)]
+    pub name: String,
+    pub spotify: SpotifyConfig,
+    pub music_dir: Option<String>,
+    /// Extra arguments passed to every spotdl invocation.
+    pub spotdl_args: Vec<String>,
+}
+
+impl Profile {
+    /// Where the Spotify token of this profile is cached between runs.
+    pub fn token_cache_path(&self) -> PathBuf {
+        crate::utils::get_data_dir().join(format!("token-cache-{}.json", self.name))
+    }
+}
+
+/// A phone, USB stick or folder playlists are mirrored to.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct Device {
+    pub path: String,
+    /// Most space the mirror may take up, e.g. `8G`. It never takes up more than
+    /// the free space of the device either.
+    pub budget: Option<String>,
+    /// Transcode to this bitrate, in kbps, to fit more on the device.
+    pub bitrate: Option<u32>,
+    /// Codec used when transcoding.
+    pub codec: Codec,
+    pub compare: Compare,
+}
+
+impl Device {
+    pub fn options(&self) -> Result<mirror::Options, String> {
+        Ok(mirror::Options {
+            budget: self.budget.as_deref().map(mirror::parse_size).transpose()?,
+            format: self.bitrate.map(|bitrate| Format {
+                codec: self.codec,
+                bitrate,
+            }),
+            compare: self.compare,
+        })
+    }
+}
+
+/// Spotify API credentials. Anything left unset falls back to the
+/// `RSPOTIFY_*` environment variables.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(default)]
+pub struct SpotifyConfig {
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+    /// File whose first line is the client secret.
+    pub client_secret_file: Option<PathBuf>,
+    /// Shell command printing the client secret, e.g. `pass show spotify/secret`.
+    pub client_secret_command: Option<String>,
+    pub redirect_uri: Option<String>,
+}
+
+impl SpotifyConfig {
+    /// Resolves the client secret from the config value, the secret file or the secret
+    /// command, in that order.
+    pub fn client_secret(&self) -> Result<Option<String>> {
+        if let Some(secret) = &self.client_secret {
+            return Ok(Some(secret.clone()));
+        }
+        if let Some(path) = &self.client_secret_file {
+            let content = fs::read_to_string(path)
+                .map_err(|e| eyre!("Unable to read {}: {}", path.display(), e))?;
+            return Ok(content.lines().next().map(|line| line.trim().to_string()));
+        }
+        if let Some(command) = &self.client_secret_command {
+            #[cfg(windows)]
+            let output = Command::new("cmd").args(["/C", command]).output()?;
+            #[cfg(not(windows))]
+            let output = Command::new("sh").args(["-c", command]).output()?;
+            if !output.status.success() {
+                return Err(eyre!("`{}` exited with {}", command, output.status));
+            }
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            return Ok(stdout.lines().next().map(|line| line.trim().to_string()));
+        }
+        Ok(None)
+    }
 }
 
 #[derive(Clone, Debug, Default, Deserialize)]
@@ -42,15 +212,14 @@ impl Config {
             .set_default("_data_dir", data_dir.to_str().unwrap())?
             .set_default("_config_dir", config_dir.to_str().unwrap())?;
 
-        let config_files = [
-            ("config.json5", config::FileFormat::Json5),
-            ("config.json", config::FileFormat::Json),
-            ("config.yaml", config::FileFormat::Yaml),
-            ("config.toml", config::FileFormat::Toml),
-            ("config.ini", config::FileFormat::Ini),
-        ];
+        // Credentials saved by the setup wizard, overridden by the user's own config files
+        builder = builder.add_source(
+            config::File::from(config_dir.join(CREDENTIALS_FILE))
+                .format(config::FileFormat::Json5)
+                .required(false),
+        );
         let mut found_config = false;
-        for (file, format) in &config_files {
+        for (file, format) in &CONFIG_FILES {
             builder = builder.add_source(
                 config::File::from(config_dir.join(file))
                     .format(*format)
@@ -74,6 +243,24 @@ impl Config {
                     .or_insert_with(|| cmd.clone());
             }
         }
+        let theme = match cfg.config.theme.as_deref() {
+            None | Some("default") => None,
+            Some(name) => {
+                let theme = builtin_theme(name);
+                if theme.is_none() {
+                    log::error!("Unknown theme `{}`, using the default one", name);
+                }
+                theme
+            }
+        };
+        for (mode, theme_styles) in theme.iter().flat_map(|theme| theme.iter()) {
+            let user_styles = cfg.styles.entry(*mode).or_default();
+            for (style_key, style) in theme_styles.iter() {
+                user_styles
+                    .entry(style_key.clone())
+                    .or_insert_with(|| *style);
+            }
+        }
         for (mode, default_styles) in default_config.styles.iter() {
             let user_styles = cfg.styles.entry(*mode).or_default();
             for (style_key, style) in default_styles.iter() {
@@ -85,6 +272,287 @@ impl Config {
 
         Ok(cfg)
     }
+
+    /// Loads the config, falling back to the built-in defaults if it cannot be
+    /// loaded at all. Every problem found in the user's config files is returned
+    /// alongside so it can be reported without aborting.
+    pub fn load() -> (Self, Vec<ConfigIssue>) {
+        let mut issues = check_config();
+        match Self::new() {
+            Ok(cfg) => (cfg, issues),
+            Err(err) => {
+                if issues.is_empty() {
+                    issues.push(ConfigIssue::new(crate::utils::get_config_dir(), "", err));
+                }
+                (Self::defaults(), issues)
+            }
+        }
+    }
+
+    /// The built-in default config, ignoring the user's config files.
+    pub fn defaults() -> Self {
+        let mut cfg: Self = json5::from_str(CONFIG).unwrap();
+        cfg.config._data_dir = crate::utils::get_data_dir();
+        cfg.config._config_dir = crate::utils::get_config_dir();
+        cfg
+    }
+
+    /// Every configured music folder: the library roots, then the folders of the
+    /// profiles, without duplicates.
+    pub fn library_roots(&self) -> Vec<String> {
+        let mut roots = self.config.library_roots.clone();
+        let mut profiles = self.config.profiles.iter().collect::<Vec<_>>();
+        profiles.sort_by_key(|(name, _)| *name);
+        for dir in profiles
+            .into_iter()
+            .filter_map(|(_, profile)| profile.music_dir.clone())
+        {
+            if !roots.contains(&dir) {
+                roots.push(dir);
+            }
+        }
+        roots
+    }
+
+    pub fn chord_timeout(&self) -> Duration {
+        self.config
+            .chord_timeout_ms
+            .map_or(crate::chord::DEFAULT_TIMEOUT, Duration::from_millis)
+    }
+
+    /// The configured devices, sorted by name.
+    pub fn devices(&self) -> Vec<(String, Device)> {
+        let mut devices = self
+            .config
+            .devices
+            .iter()
+            .map(|(name, device)| (name.clone(), device.clone()))
+            .collect::<Vec<_>>();
+        devices.sort_by(|a, b| a.0.cmp(&b.0));
+        devices
+    }
+
+    pub fn transcoder(&self) -> Transcoder {
+        let jobs = self
+            .config
+            .transcode_jobs
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(1, |jobs| jobs.get()));
+        let transcoder = Transcoder::new(self.config._data_dir.join(transcode::CACHE_DIR), jobs);
+        match self.config.transcode_cache_mb {
+            Some(mb) => transcoder.max_size(mb << 20),
+            None => transcoder,
+        }
+    }
+
+    pub fn verify_tolerance(&self) -> Duration {
+        self.config
+            .verify_tolerance_secs
+            .map_or(crate::verify::DEFAULT_TOLERANCE, Duration::from_secs)
+    }
+
+    /// Looks up a profile by name. The default profile is built from the top-level
+    /// `spotify` settings unless it is declared under `profiles`.
+    pub fn profile(&self, name: &str) -> Result<Profile> {
+        let mut profile = match self.config.profiles.get(name) {
+            Some(profile) => profile.clone(),
+            None if name == DEFAULT_PROFILE => Profile {
+                spotify: self.config.spotify.clone(),
+                ..Default::default()
+            },
+            None => return Err(eyre!("No profile named `{}` in the config", name)),
+        };
+        profile.name = name.to_string();
+        Ok(profile)
+    }
+
+    pub fn profile_names(&self) -> Vec<String> {
+        let mut names = self.config.profiles.keys().cloned().collect::<Vec<_>>();
+        if !names.iter().any(|name| name == DEFAULT_PROFILE) {
+            names.push(DEFAULT_PROFILE.to_string());
+        }
+        names.sort();
+        names
+    }
+}
+
+/// Writes the credentials collected by the setup wizard for `profile` to the config
+/// directory, keeping the ones already saved for other profiles.
+pub fn save_credentials(profile: &str, spotify: &SpotifyConfig) -> Result<PathBuf> {
+    let dir = crate::utils::get_config_dir();
+    fs::create_dir_all(&dir)?;
+    let path = dir.join(CREDENTIALS_FILE);
+    let mut saved: JsonValue = match fs::read_to_string(&path) {
+        Ok(content) => json5::from_str(&content)?,
+        Err(_) => serde_json::json!({}),
+    };
+    let value = serde_json::to_value(spotify)?;
+    if profile == DEFAULT_PROFILE {
+        saved["spotify"] = value;
+    } else {
+        saved["profiles"][profile]["spotify"] = value;
+    }
+    let content = serde_json::to_string_pretty(&saved)?;
+
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    std::io::Write::write_all(&mut options.open(&path)?, content.as_bytes())?;
+    Ok(path)
+}
+
+/// A problem found in one of the user's config files.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConfigIssue {
+    pub file: PathBuf,
+    /// Dotted path of the offending setting, empty if the whole file is affected.
+    pub key: String,
+    pub message: String,
+}
+
+impl ConfigIssue {
+    fn new(file: PathBuf, key: &str, message: impl fmt::Display) -> Self {
+        Self {
+            file,
+            key: key.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.key.is_empty() {
+            write!(f, "{}: {}", self.file.display(), self.message)
+        } else {
+            write!(f, "{}: {}: {}", self.file.display(), self.key, self.message)
+        }
+    }
+}
+
+/// The parts of a config file that are validated entry by entry.
+#[derive(Debug, Default, Deserialize)]
+struct RawConfig {
+    #[serde(default)]
+    keybindings: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
+    styles: HashMap<String, HashMap<String, String>>,
+    #[serde(default)]
+    theme: Option<String>,
+}
+
+/// Checks every config file in the config directory on its own and collects all
+/// the problems found in them.
+pub fn check_config() -> Vec<ConfigIssue> {
+    let mut issues = vec![];
+    for (path, format) in config_paths() {
+        if path.exists() {
+            issues.extend(check_file(&path, format));
+        }
+    }
+    issues
+}
+
+/// Every file the config is built from, existing or not.
+fn config_paths() -> impl Iterator<Item = (PathBuf, config::FileFormat)> {
+    let dir = crate::utils::get_config_dir();
+    std::iter::once((CREDENTIALS_FILE, config::FileFormat::Json5))
+        .chain(CONFIG_FILES)
+        .map(move |(name, format)| (dir.join(name), format))
+}
+
+/// Notices edits to the config files by polling their modification times.
+#[derive(Debug, Default)]
+pub struct ConfigWatcher {
+    stamps: Vec<Option<SystemTime>>,
+}
+
+impl ConfigWatcher {
+    pub fn new() -> Self {
+        Self {
+            stamps: Self::scan(),
+        }
+    }
+
+    fn scan() -> Vec<Option<SystemTime>> {
+        config_paths()
+            .map(|(path, _)| fs::metadata(path).and_then(|meta| meta.modified()).ok())
+            .collect()
+    }
+
+    /// Whether a config file was created, modified or removed since the last call.
+    pub fn changed(&mut self) -> bool {
+        let stamps = Self::scan();
+        if stamps == self.stamps {
+            return false;
+        }
+        self.stamps = stamps;
+        true
+    }
+}
+
+fn check_file(path: &Path, format: config::FileFormat) -> Vec<ConfigIssue> {
+    let issue = |key: &str, message: &dyn fmt::Display| ConfigIssue::new(path.into(), key, message);
+    let source = match config::Config::builder()
+        .add_source(config::File::from(path).format(format))
+        .build()
+    {
+        Ok(source) => source,
+        Err(err) => return vec![issue("", &err)],
+    };
+    let raw: RawConfig = match source.clone().try_deserialize() {
+        Ok(raw) => raw,
+        Err(err) => return vec![issue("", &err)],
+    };
+
+    let mut issues = vec![];
+    if let Err(err) = source.try_deserialize::<AppConfig>() {
+        issues.push(issue("", &err));
+    }
+    for (mode, bindings) in &raw.keybindings {
+        if let Err(err) = parse_mode(mode) {
+            issues.push(issue(&format!("keybindings.{}", mode), &err));
+            continue;
+        }
+        for (keys, action) in bindings {
+            let key = format!("keybindings.{}.{}", mode, keys);
+            if let Err(err) = parse_key_sequence(keys) {
+                issues.push(issue(&key, &err));
+            }
+            if let Err(err) = parse_action(action) {
+                issues.push(issue(&key, &err));
+            }
+        }
+    }
+    for (mode, styles) in &raw.styles {
+        if let Err(err) = parse_mode(mode) {
+            issues.push(issue(&format!("styles.{}", mode), &err));
+            continue;
+        }
+        for (name, style) in styles {
+            if let Err(err) = check_style(style) {
+                issues.push(issue(&format!("styles.{}.{}", mode, name), &err));
+            }
+        }
+    }
+    if let Some(theme) = raw.theme {
+        if theme != "default" && builtin_theme(&theme).is_none() {
+            let known = std::iter::once("default")
+                .chain(THEMES.iter().map(|(name, _)| *name))
+                .collect::<Vec<_>>();
+            let message = format!(
+                "Unknown theme `{}`, expected one of {}",
+                theme,
+                known.join(", ")
+            );
+            issues.push(issue("theme", &message));
+        }
+    }
+    issues.sort_by(|a, b| a.key.cmp(&b.key));
+    issues
 }
 
 #[derive(Clone, Debug, Default, Deref, DerefMut)]
@@ -95,23 +563,43 @@ impl<'de> Deserialize<'de> for KeyBindings {
     where
         D: Deserializer<'de>,
     {
-        let parsed_map = HashMap::<Mode, HashMap<String, Action>>::deserialize(deserializer)?;
+        let parsed_map = HashMap::<String, HashMap<String, String>>::deserialize(deserializer)?;
 
-        let keybindings = parsed_map
-            .into_iter()
-            .map(|(mode, inner_map)| {
-                let converted_inner_map = inner_map
-                    .into_iter()
-                    .map(|(key_str, cmd)| (parse_key_sequence(&key_str).unwrap(), cmd))
-                    .collect();
-                (mode, converted_inner_map)
-            })
-            .collect();
+        // Invalid entries are skipped here and reported by `check_config`
+        let mut keybindings = HashMap::new();
+        for (mode, inner_map) in parsed_map {
+            let Ok(mode) = parse_mode(&mode) else {
+                continue;
+            };
+            let bindings: &mut HashMap<_, _> = keybindings.entry(mode).or_default();
+            for (key_str, cmd) in inner_map {
+                if let (Ok(keys), Ok(action)) = (parse_key_sequence(&key_str), parse_action(&cmd)) {
+                    bindings.insert(keys, action);
+                }
+            }
+        }
 
         Ok(KeyBindings(keybindings))
     }
 }
 
+/// Parses a mode name, ignoring the case of its first letter since the config
+/// crate lowercases the keys of the user's config files.
+fn parse_mode(raw: &str) -> Result<Mode, String> {
+    let mut chars = raw.chars();
+    let capitalized = chars
+        .next()
+        .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+        .unwrap_or_default();
+    Mode::deserialize(capitalized.as_str().into_deserializer())
+        .map_err(|_: de::value::Error| format!("Unknown mode `{}`", raw))
+}
+
+fn parse_action(raw: &str) -> Result<Action, String> {
+    Action::deserialize(raw.into_deserializer())
+        .map_err(|_: de::value::Error| format!("Unknown action `{}`", raw))
+}
+
 fn parse_key_event(raw: &str) -> Result<KeyEvent, String> {
     let raw_lower = raw.to_ascii_lowercase();
     let (remaining, modifiers) = extract_modifiers(&raw_lower);
@@ -256,6 +744,14 @@ pub fn key_event_to_string(key_event: &KeyEvent) -> String {
     key
 }
 
+/// Space separated names of `keys`, as shown in the help overlay.
+pub fn key_sequence_to_string(keys: &[KeyEvent]) -> String {
+    keys.iter()
+        .map(key_event_to_string)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 pub fn parse_key_sequence(raw: &str) -> Result<Vec<KeyEvent>, String> {
     if raw.chars().filter(|c| *c == '>').count() != raw.chars().filter(|c| *c == '<').count() {
         return Err(format!("Unable to parse `{}`", raw));
@@ -291,10 +787,11 @@ impl<'de> Deserialize<'de> for Styles {
     where
         D: Deserializer<'de>,
     {
-        let parsed_map = HashMap::<Mode, HashMap<String, String>>::deserialize(deserializer)?;
+        let parsed_map = HashMap::<String, HashMap<String, String>>::deserialize(deserializer)?;
 
         let styles = parsed_map
             .into_iter()
+            .filter_map(|(mode, inner_map)| Some((parse_mode(&mode).ok()?, inner_map)))
             .map(|(mode, inner_map)| {
                 let converted_inner_map = inner_map
                     .into_iter()
@@ -308,6 +805,41 @@ impl<'de> Deserialize<'de> for Styles {
     }
 }
 
+impl Styles {
+    /// Looks up a named style for `mode`, falling back to the one set for `Home`.
+    pub fn get_style(&self, mode: Mode, key: &str) -> Style {
+        self.get(&mode)
+            .and_then(|styles| styles.get(key))
+            .or_else(|| self.get(&Mode::Home).and_then(|styles| styles.get(key)))
+            .copied()
+            .unwrap_or_default()
+    }
+}
+
+/// Styles of the built-in theme called `name`.
+pub fn builtin_theme(name: &str) -> Option<Styles> {
+    let (_, source) = THEMES.iter().find(|(theme, _)| *theme == name)?;
+    json5::from_str(source).ok()
+}
+
+/// Reports the colors in a style string that `parse_style` would ignore.
+fn check_style(line: &str) -> Result<(), String> {
+    let (foreground, background) =
+        line.split_at(line.to_lowercase().find("on ").unwrap_or(line.len()));
+    for part in [foreground, &background.replace("on ", "")] {
+        let (color, _) = process_color_string(part);
+        let color = color
+            .split_whitespace()
+            .filter(|word| !["bold", "underline", "inverse", "dim"].contains(word))
+            .collect::<Vec<_>>()
+            .join(" ");
+        if !color.is_empty() && parse_color(&color).is_none() {
+            return Err(format!("Unknown color `{}`", color));
+        }
+    }
+    Ok(())
+}
+
 pub fn parse_style(line: &str) -> Style {
     let (foreground, background) =
         line.split_at(line.to_lowercase().find("on ").unwrap_or(line.len()));
@@ -331,7 +863,8 @@ fn process_color_string(color_str: &str) -> (String, Modifier) {
         .replace("bright ", "")
         .replace("bold ", "")
         .replace("underline ", "")
-        .replace("inverse ", "");
+        .replace("inverse ", "")
+        .replace("dim ", "");
 
     let mut modifiers = Modifier::empty();
     if color_str.contains("underline") {
@@ -343,6 +876,9 @@ fn process_color_string(color_str: &str) -> (String, Modifier) {
     if color_str.contains("inverse") {
         modifiers |= Modifier::REVERSED;
     }
+    if color_str.contains("dim") {
+        modifiers |= Modifier::DIM;
+    }
 
     (color, modifiers)
 }
@@ -350,7 +886,13 @@ fn process_color_string(color_str: &str) -> (String, Modifier) {
 fn parse_color(s: &str) -> Option<Color> {
     let s = s.trim_start();
     let s = s.trim_end();
-    if s.contains("bright color") {
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 {
+            return None;
+        }
+        let rgb = u32::from_str_radix(hex, 16).ok()?;
+        Some(Color::Rgb((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8))
+    } else if s.contains("bright color") {
         let s = s.trim_start_matches("bright ");
         let c = s
             .trim_start_matches("color")
@@ -369,10 +911,14 @@ fn parse_color(s: &str) -> Option<Color> {
                 .parse::<u8>()
                 .unwrap_or_default();
         Some(Color::Indexed(c))
-    } else if s.contains("rgb") {
-        let red = (s.as_bytes()[3] as char).to_digit(10).unwrap_or_default() as u8;
-        let green = (s.as_bytes()[4] as char).to_digit(10).unwrap_or_default() as u8;
-        let blue = (s.as_bytes()[5] as char).to_digit(10).unwrap_or_default() as u8;
+    } else if let Some(digits) = s.strip_prefix("rgb") {
+        let levels = digits
+            .chars()
+            .map(|c| c.to_digit(6).map(|d| d as u8))
+            .collect::<Option<Vec<_>>>()?;
+        let [red, green, blue] = levels[..] else {
+            return None;
+        };
         let c = 16 + red * 36 + green * 6 + blue;
         Some(Color::Indexed(c))
     } else if s == "bold black" {
@@ -417,6 +963,7 @@ mod tests {
     use pretty_assertions::assert_eq;
 
     use super::*;
+    use crate::utils::testing::TestDir;
 
     #[test]
     fn test_parse_style_default() {
@@ -459,6 +1006,69 @@ mod tests {
         assert_eq!(color, Some(Color::Indexed(expected)));
     }
 
+    #[test]
+    fn test_parse_color_hex() {
+        assert_eq!(parse_color("#1db954"), Some(Color::Rgb(0x1d, 0xb9, 0x54)));
+        assert_eq!(parse_color("#12345"), None);
+        let style = parse_style("bold #ffffff on #000000");
+        assert_eq!(style.fg, Some(Color::Rgb(255, 255, 255)));
+        assert_eq!(style.bg, Some(Color::Rgb(0, 0, 0)));
+        assert!(style.add_modifier.contains(Modifier::BOLD));
+    }
+
+    #[test]
+    fn test_check_file_reports_every_issue() -> Result<()> {
+        let dir = TestDir::new("check");
+        let path = dir.join("check.json5");
+        fs::write(
+            &path,
+            r#"{
+                "keybindings": {
+                    "Home": { "<q>": "Quit", "<Ctrl-nope>": "Quit", "<x>": "Explode" },
+                    "Nowhere": { "<q>": "Quit" },
+                },
+                "styles": { "Home": { "highlight": "black on mauve", "match": "bold #ff0000" } },
+                "theme": "neon",
+            }"#,
+        )?;
+        let issues = check_file(&path, config::FileFormat::Json5);
+
+        let keys = issues.iter().map(|i| i.key.as_str()).collect::<Vec<_>>();
+        assert_eq!(
+            keys,
+            vec![
+                "keybindings.home.<ctrl-nope>",
+                "keybindings.home.<x>",
+                "keybindings.nowhere",
+                "styles.home.highlight",
+                "theme",
+            ]
+        );
+        assert_eq!(issues[3].message, "Unknown color `mauve`");
+        Ok(())
+    }
+
+    #[test]
+    fn test_invalid_keybindings_are_skipped() {
+        let keybindings: KeyBindings =
+            json5::from_str(r#"{ "Home": { "<q>": "Quit", "<x>": "Explode" }, "Nowhere": {} }"#)
+                .unwrap();
+        assert_eq!(keybindings.len(), 1);
+        assert_eq!(keybindings[&Mode::Home].len(), 1);
+    }
+
+    #[test]
+    fn test_builtin_themes() {
+        for (name, _) in THEMES {
+            let theme = builtin_theme(name).unwrap();
+            assert_ne!(
+                theme.get_style(Mode::Downloader, "highlight"),
+                Style::default()
+            );
+        }
+        assert!(builtin_theme("neon").is_none());
+    }
+
     #[test]
     fn test_parse_color_unknown() {
         let color = parse_color("unknown");
@@ -476,6 +1086,74 @@ mod tests {
                 .unwrap(),
             &Action::Quit
         );
+        let downloader = c.keybindings.get(&Mode::Downloader).unwrap();
+        assert_eq!(
+            downloader.get(&parse_key_sequence("</>").unwrap()),
+            Some(&Action::Filter)
+        );
+        assert_eq!(
+            downloader.get(&vec![KeyEvent::new(
+                KeyCode::Char('G'),
+                KeyModifiers::SHIFT
+            )]),
+            Some(&Action::Bottom)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_client_secret_sources() -> Result<()> {
+        let dir = TestDir::new("secret");
+        let path = dir.join("secret");
+        fs::write(&path, "from-file\nignored\n")?;
+        let from_file = SpotifyConfig {
+            client_secret_file: Some(path.clone()),
+            ..Default::default()
+        };
+        assert_eq!(from_file.client_secret()?, Some("from-file".to_string()));
+
+        let inline = SpotifyConfig {
+            client_secret: Some("inline".to_string()),
+            client_secret_command: Some("exit 1".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(inline.client_secret()?, Some("inline".to_string()));
+
+        #[cfg(unix)]
+        {
+            let from_command = SpotifyConfig {
+                client_secret_command: Some("echo from-command".to_string()),
+                ..Default::default()
+            };
+            assert_eq!(
+                from_command.client_secret()?,
+                Some("from-command".to_string())
+            );
+        }
+        assert_eq!(SpotifyConfig::default().client_secret()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn test_profiles() -> Result<()> {
+        let mut c = Config::default();
+        c.config.spotify.client_id = Some("shared".to_string());
+        c.config.profiles.insert(
+            "alice".to_string(),
+            Profile {
+                music_dir: Some("/music/alice".to_string()),
+                ..Default::default()
+            },
+        );
+        assert_eq!(c.profile_names(), vec!["alice", "default"]);
+        let alice = c.profile("alice")?;
+        assert_eq!(alice.name, "alice");
+        assert_eq!(alice.music_dir.as_deref(), Some("/music/alice"));
+        assert_eq!(
+            c.profile(DEFAULT_PROFILE)?.spotify.client_id.as_deref(),
+            Some("shared")
+        );
+        assert!(c.profile("bob").is_err());
         Ok(())
     }
 
@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    path::Path,
+    process::{Command, Stdio},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    components::spotify::{resolve_credentials, resolve_oauth},
+    config::SpotifyConfig,
+};
+
+/// External programs spotia shells out to, with the flag that prints their version.
+pub const REQUIRED_TOOLS: [(&str, &str); 3] =
+    [("spotdl", "--version"), ("zip", "-v"), ("unzip", "-v")];
+
+/// Below this much free space in the music directory the disk check fails.
+const MIN_FREE_BYTES: u64 = 1024 * 1024 * 1024;
+
+/// Outcome of a single diagnostic check.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Check {
+    pub name: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl Check {
+    fn pass(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: true,
+            detail: detail.into(),
+        }
+    }
+
+    fn fail(name: &str, detail: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            passed: false,
+            detail: detail.into(),
+        }
+    }
+}
+
+/// Runs every check. The music directory checks are skipped when no directory is given.
+pub fn run_checks(spotify: &SpotifyConfig, music_dir: Option<&Path>) -> Vec<Check> {
+    let mut checks = check_tools();
+    checks.push(check_credentials(spotify));
+    if let Some(dir) = music_dir {
+        checks.push(check_writable(dir));
+        checks.push(check_disk_space(dir));
+    }
+    checks
+}
+
+pub fn check_tools() -> Vec<Check> {
+    REQUIRED_TOOLS
+        .iter()
+        .map(|(tool, flag)| check_tool(tool, flag))
+        .collect()
+}
+
+pub fn check_tool(tool: &str, version_flag: &str) -> Check {
+    match Command::new(tool)
+        .arg(version_flag)
+        .stdin(Stdio::null())
+        .output()
+    {
+        Ok(output) if output.status.success() => {
+            let stdout = String::from_utf8_lossy(&output.stdout);
+            Check::pass(tool, version_line(tool, &stdout).unwrap_or("installed"))
+        }
+        Ok(output) => {
+            // A broken install, e.g. a Python import error, still exits with a status
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            match stderr.lines().rev().find(|line| !line.trim().is_empty()) {
+                Some(line) => Check::fail(tool, format!("{} ({})", output.status, line.trim())),
+                None => Check::fail(tool, output.status.to_string()),
+            }
+        }
+        Err(err) => Check::fail(tool, format!("not found in PATH ({})", err)),
+    }
+}
+
+pub fn check_credentials(spotify: &SpotifyConfig) -> Check {
+    let name = "Spotify credentials";
+    match resolve_credentials(spotify) {
+        Err(err) => Check::fail(name, format!("unable to read the client secret ({})", err)),
+        Ok(None) => Check::fail(name, "no client id or secret in the config or environment"),
+        Ok(Some(_)) if resolve_oauth(spotify).is_none() => {
+            Check::fail(name, "no redirect URI in the config or environment")
+        }
+        Ok(Some(_)) => Check::pass(name, "configured"),
+    }
+}
+
+pub fn check_writable(dir: &Path) -> Check {
+    let name = "Music directory";
+    if !dir.is_dir() {
+        return Check::fail(name, format!("{} does not exist", dir.display()));
+    }
+    let probe = dir.join(".spotia-write-test");
+    match fs::write(&probe, b"") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+            Check::pass(name, format!("{} is writable", dir.display()))
+        }
+        Err(err) => Check::fail(name, format!("{} is not writable ({})", dir.display(), err)),
+    }
+}
+
+pub fn check_disk_space(dir: &Path) -> Check {
+    let name = "Free disk space";
+    match free_space(dir) {
+        Some(bytes) if bytes >= MIN_FREE_BYTES => Check::pass(name, format_size(bytes)),
+        Some(bytes) => Check::fail(name, format!("only {} left", format_size(bytes))),
+        None => Check::fail(name, format!("unable to query {}", dir.display())),
+    }
+}
+
+/// Free space available to the current user on the filesystem holding `path`.
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> Option<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let path = CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    // SAFETY: `path` is a valid NUL terminated string and `stat` is a valid out pointer.
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Some(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(not(unix))]
+pub fn free_space(_path: &Path) -> Option<u64> {
+    None
+}
+
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{:.1} {}", size, UNITS[unit])
+}
+
+/// Picks the line announcing the version, e.g. `This is Zip 3.0 (July 5th 2008)`,
+/// falling back to the first non-empty line for tools that only print a number.
+fn version_line<'a>(tool: &str, output: &'a str) -> Option<&'a str> {
+    output
+        .lines()
+        .find(|line| {
+            let words = line.split_whitespace().collect::<Vec<_>>();
+            words.windows(2).any(|pair| {
+                pair[0].eq_ignore_ascii_case(tool)
+                    && pair[1].starts_with(|c: char| c.is_ascii_digit())
+            })
+        })
+        .or_else(|| output.lines().find(|line| !line.trim().is_empty()))
+        .map(str::trim)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_version_line() {
+        let zip = "Copyright (c) 1990-2008 Info-ZIP - Type 'zip \"-L\"' for software license.\nThis is Zip 3.0 (July 5th 2008), by Info-ZIP.\n";
+        assert_eq!(
+            version_line("zip", zip),
+            Some("This is Zip 3.0 (July 5th 2008), by Info-ZIP.")
+        );
+        assert_eq!(version_line("spotdl", "\n4.2.5\n"), Some("4.2.5"));
+        assert_eq!(version_line("unzip", ""), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_check_tool_fails_on_error_status() {
+        assert!(check_tool("true", "--version").passed);
+        // `sh -c` without a command is an error
+        let check = check_tool("sh", "-c");
+        assert!(!check.passed);
+        assert!(check.detail.starts_with("exit status"));
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512.0 B");
+        assert_eq!(format_size(3 * 1024 * 1024 * 1024), "3.0 GiB");
+    }
+}
@@ -0,0 +1,184 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use color_eyre::eyre::Result;
+use rspotify::model::{PlayableItem, PlaylistItem};
+use serde::{Deserialize, Serialize};
+
+/// File format used when exporting playlist metadata.
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Json => "json",
+        }
+    }
+}
+
+/// One exported row: a playlist entry flattened with its track metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrackRecord {
+    pub playlist: String,
+    pub name: String,
+    pub artists: String,
+    pub album: String,
+    pub isrc: String,
+    pub duration_ms: i64,
+    pub added_at: String,
+    pub added_by: String,
+}
+
+impl TrackRecord {
+    /// Builds a record from a playlist item, skipping episodes and removed tracks.
+    pub fn from_item(playlist: &str, item: &PlaylistItem) -> Option<Self> {
+        let Some(PlayableItem::Track(track)) = &item.track else {
+            return None;
+        };
+        let artists = track
+            .artists
+            .iter()
+            .map(|artist| artist.name.as_str())
+            .collect::<Vec<_>>()
+            .join("; ");
+        Some(Self {
+            playlist: playlist.to_string(),
+            name: track.name.clone(),
+            artists,
+            album: track.album.name.clone(),
+            isrc: track.external_ids.get("isrc").cloned().unwrap_or_default(),
+            duration_ms: track.duration.num_milliseconds(),
+            added_at: item
+                .added_at
+                .map(|date| date.to_rfc3339())
+                .unwrap_or_default(),
+            added_by: item
+                .added_by
+                .as_ref()
+                .map(|user| user.id.to_string())
+                .unwrap_or_default(),
+        })
+    }
+}
+
+pub fn write_records<W: Write>(
+    records: &[TrackRecord],
+    format: ExportFormat,
+    writer: W,
+) -> Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            let mut csv = csv::Writer::from_writer(writer);
+            for record in records {
+                csv.serialize(record)?;
+            }
+            csv.flush()?;
+        }
+        ExportFormat::Json => serde_json::to_writer_pretty(writer, records)?,
+    }
+    Ok(())
+}
+
+pub fn export_to_file(records: &[TrackRecord], format: ExportFormat, path: &Path) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let file = File::create(path)?;
+    write_records(records, format, BufWriter::new(file))
+}
+
+/// Where to export `playlists` in `dir`: named after the playlist when there is
+/// only one, and numbered so an earlier export is never overwritten.
+pub fn export_path(dir: &Path, playlists: &[String], format: ExportFormat) -> PathBuf {
+    let name = match playlists {
+        [name] => format!(
+            "spotia-export-{}",
+            name.replace(['/', '\\', ':', '*', '?', '"', '<', '>', '|'], "_")
+        ),
+        _ => "spotia-export".to_string(),
+    };
+    let mut path = dir.join(format!("{}.{}", name, format.extension()));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{} {}.{}", name, n, format.extension()));
+        n += 1;
+    }
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::utils::testing::TestDir;
+
+    fn record() -> TrackRecord {
+        TrackRecord {
+            playlist: "Road, trip".to_string(),
+            name: "Song \"A\"".to_string(),
+            artists: "One; Two".to_string(),
+            album: "Album".to_string(),
+            isrc: "USRC17607839".to_string(),
+            duration_ms: 215000,
+            added_at: "2024-01-01T00:00:00+00:00".to_string(),
+            added_by: "someone".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_write_csv_escapes_fields() -> Result<()> {
+        let mut out = vec![];
+        write_records(&[record()], ExportFormat::Csv, &mut out)?;
+        let out = String::from_utf8(out)?;
+        let mut lines = out.lines();
+        assert_eq!(
+            lines.next(),
+            Some("playlist,name,artists,album,isrc,duration_ms,added_at,added_by")
+        );
+        assert_eq!(
+            lines.next(),
+            Some("\"Road, trip\",\"Song \"\"A\"\"\",One; Two,Album,USRC17607839,215000,2024-01-01T00:00:00+00:00,someone")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_export_path_never_overwrites() -> Result<()> {
+        let dir = TestDir::new("export-path");
+        let one = vec!["AC/DC: Live".to_string()];
+        let path = export_path(&dir, &one, ExportFormat::Csv);
+        assert_eq!(path, dir.join("spotia-export-AC_DC_ Live.csv"));
+        std::fs::write(&path, "")?;
+        assert_eq!(
+            export_path(&dir, &one, ExportFormat::Csv),
+            dir.join("spotia-export-AC_DC_ Live 2.csv")
+        );
+        let two = vec!["A".to_string(), "B".to_string()];
+        assert_eq!(
+            export_path(&dir, &two, ExportFormat::Json),
+            dir.join("spotia-export.json")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_write_json_roundtrip() -> Result<()> {
+        let mut out = vec![];
+        write_records(&[record()], ExportFormat::Json, &mut out)?;
+        let parsed: Vec<TrackRecord> = serde_json::from_slice(&out)?;
+        assert_eq!(parsed, vec![record()]);
+        Ok(())
+    }
+}
@@ -0,0 +1,88 @@
+/// A successful fuzzy match of a pattern against a candidate string.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub score: i64,
+    /// Char positions in the candidate that matched the pattern.
+    pub positions: Vec<usize>,
+}
+
+/// Matches `pattern` as a case-insensitive subsequence of `text`. Consecutive
+/// characters and characters at the start of a word score higher.
+pub fn fuzzy_match(pattern: &str, text: &str) -> Option<FuzzyMatch> {
+    let pattern = pattern.to_lowercase().chars().collect::<Vec<_>>();
+    let mut positions = Vec::with_capacity(pattern.len());
+    let mut score = 0;
+    let mut prev: Option<char> = None;
+    let mut next = 0;
+
+    for (i, c) in text.chars().enumerate() {
+        if next == pattern.len() {
+            break;
+        }
+        if c.to_lowercase().eq(pattern[next].to_lowercase()) {
+            score += 1;
+            if positions.last().is_some_and(|&last| last + 1 == i) {
+                score += 5;
+            }
+            if prev.is_none_or(|p| !p.is_alphanumeric()) {
+                score += 3;
+            }
+            positions.push(i);
+            next += 1;
+        }
+        prev = Some(c);
+    }
+
+    if next < pattern.len() {
+        return None;
+    }
+    if let Some(&first) = positions.first() {
+        score -= first as i64 / 4;
+    }
+    Some(FuzzyMatch { score, positions })
+}
+
+/// Filters `items` by `pattern`, returning the indexes into `items` of the matches
+/// ordered from best to worst, along with their matched positions. An empty
+/// pattern keeps every item in its original order.
+pub fn filter<'a>(
+    pattern: &str,
+    items: impl IntoIterator<Item = &'a str>,
+) -> Vec<(usize, Vec<usize>)> {
+    let mut matches = items
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, item)| fuzzy_match(pattern, item).map(|m| (idx, m)))
+        .collect::<Vec<_>>();
+    matches.sort_by_key(|(_, m)| std::cmp::Reverse(m.score));
+    matches
+        .into_iter()
+        .map(|(idx, m)| (idx, m.positions))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_match() {
+        let m = fuzzy_match("dsc", "Daily Mix Disco").unwrap();
+        assert_eq!(m.positions, vec![0, 12, 13]);
+        assert!(fuzzy_match("xyz", "Daily Mix").is_none());
+        assert!(fuzzy_match("", "anything").unwrap().positions.is_empty());
+    }
+
+    #[test]
+    fn test_filter_maps_back_to_indexes() {
+        let items = ["Chill", "Road Trip", "Rock Classics", "Workout"];
+        let matched = filter("rk", items)
+            .into_iter()
+            .map(|(idx, _)| idx)
+            .collect::<Vec<_>>();
+        assert_eq!(matched, vec![3, 2]);
+        assert_eq!(filter("", items).len(), items.len());
+    }
+}
@@ -0,0 +1,236 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::Read,
+    path::Path,
+};
+
+use color_eyre::eyre::{eyre, Result};
+use serde::{Deserialize, Serialize};
+
+/// File extensions that can be imported as playlists.
+pub const IMPORT_EXTENSIONS: [&str; 3] = ["m3u", "m3u8", "csv"];
+
+/// A single entry read from a local playlist file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImportEntry {
+    pub artist: String,
+    pub title: String,
+    pub isrc: Option<String>,
+}
+
+impl ImportEntry {
+    pub fn label(&self) -> String {
+        if self.artist.is_empty() {
+            self.title.clone()
+        } else {
+            format!("{} - {}", self.artist, self.title)
+        }
+    }
+
+    /// Query used to look the entry up with the Spotify search endpoint.
+    pub fn search_query(&self) -> String {
+        if self.artist.is_empty() {
+            format!("track:{}", self.title)
+        } else {
+            format!("artist:{} track:{}", self.artist, self.title)
+        }
+    }
+}
+
+/// The Spotify track chosen for an imported entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportMatch {
+    pub entry: String,
+    pub track_uri: Option<String>,
+    pub track: String,
+    pub confidence: u8,
+    pub include: bool,
+}
+
+impl ImportMatch {
+    pub fn unmatched(entry: &ImportEntry) -> Self {
+        Self {
+            entry: entry.label(),
+            track_uri: None,
+            track: String::new(),
+            confidence: 0,
+            include: false,
+        }
+    }
+}
+
+pub fn is_importable(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| IMPORT_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+pub fn parse_file(path: &Path) -> Result<Vec<ImportEntry>> {
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+    match ext.as_str() {
+        "m3u" | "m3u8" => Ok(parse_m3u(&fs::read_to_string(path)?)),
+        "csv" => parse_csv(File::open(path)?),
+        _ => Err(eyre!("Unsupported playlist format: {}", path.display())),
+    }
+}
+
+/// Parses an (extended) M3U playlist. `#EXTINF` titles are preferred, otherwise
+/// the file name of each entry is used as `Artist - Title`.
+pub fn parse_m3u(content: &str) -> Vec<ImportEntry> {
+    let mut entries = vec![];
+    let mut pending: Option<ImportEntry> = None;
+    for line in content.lines().map(str::trim) {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            let label = info.split_once(',').map(|(_, label)| label).unwrap_or("");
+            pending = Some(split_label(label));
+        } else if line.is_empty() || line.starts_with('#') {
+            continue;
+        } else {
+            let entry = match pending.take() {
+                Some(entry) if !entry.title.is_empty() => entry,
+                _ => {
+                    let stem = Path::new(&line.replace('\\', "/"))
+                        .file_stem()
+                        .and_then(|stem| stem.to_str())
+                        .unwrap_or(line)
+                        .to_string();
+                    split_label(&stem)
+                }
+            };
+            entries.push(entry);
+        }
+    }
+    entries
+}
+
+/// Parses a CSV playlist with a header row. Column names are matched loosely so
+/// that spotia exports as well as exports from other tools can be read back.
+pub fn parse_csv<R: Read>(reader: R) -> Result<Vec<ImportEntry>> {
+    let mut csv = csv::Reader::from_reader(reader);
+    let headers = csv
+        .headers()?
+        .iter()
+        .map(|h| h.trim().to_lowercase())
+        .collect::<Vec<_>>();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+    let title = column(&["title", "name", "track", "track name"])
+        .ok_or_else(|| eyre!("CSV file has no title column"))?;
+    let artist = column(&["artist", "artists", "artist name(s)", "artist name"]);
+    let isrc = column(&["isrc"]);
+
+    let mut entries = vec![];
+    for record in csv.records() {
+        let record = record?;
+        let get = |idx: Option<usize>| {
+            idx.and_then(|idx| record.get(idx))
+                .map(|value| value.trim().to_string())
+                .unwrap_or_default()
+        };
+        let entry = ImportEntry {
+            title: get(Some(title)),
+            artist: get(artist).replace(';', ","),
+            isrc: Some(get(isrc)).filter(|isrc| !isrc.is_empty()),
+        };
+        if !entry.title.is_empty() {
+            entries.push(entry);
+        }
+    }
+    Ok(entries)
+}
+
+/// Scores how well a Spotify track matches an entry, from 0 to 100, by the
+/// share of the entry's words found in the candidate's artists and title.
+pub fn confidence(entry: &ImportEntry, artists: &str, title: &str) -> u8 {
+    let wanted = words(&format!("{} {}", entry.artist, entry.title));
+    if wanted.is_empty() {
+        return 0;
+    }
+    let found = words(&format!("{} {}", artists, title));
+    let hits = wanted.iter().filter(|word| found.contains(*word)).count();
+    (hits * 100 / wanted.len()) as u8
+}
+
+fn words(s: &str) -> HashSet<String> {
+    s.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+fn split_label(label: &str) -> ImportEntry {
+    match label.split_once(" - ") {
+        Some((artist, title)) => ImportEntry {
+            artist: artist.trim().to_string(),
+            title: title.trim().to_string(),
+            isrc: None,
+        },
+        None => ImportEntry {
+            title: label.trim().to_string(),
+            ..Default::default()
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_parse_extended_m3u() {
+        let entries = parse_m3u(
+            "#EXTM3U\n#EXTINF:215,Daft Punk - One More Time\nmusic/one.mp3\n\nmusic/Air - La Femme d'Argent.flac\n",
+        );
+        assert_eq!(
+            entries,
+            vec![
+                ImportEntry {
+                    artist: "Daft Punk".to_string(),
+                    title: "One More Time".to_string(),
+                    isrc: None,
+                },
+                ImportEntry {
+                    artist: "Air".to_string(),
+                    title: "La Femme d'Argent".to_string(),
+                    isrc: None,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_csv_export() -> Result<()> {
+        let csv = "playlist,name,artists,album,isrc\nMix,Song,One; Two,Album,USRC17607839\nMix,,Nobody,,\n";
+        let entries = parse_csv(csv.as_bytes())?;
+        assert_eq!(
+            entries,
+            vec![ImportEntry {
+                artist: "One, Two".to_string(),
+                title: "Song".to_string(),
+                isrc: Some("USRC17607839".to_string()),
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_csv_without_title() {
+        assert!(parse_csv("artist,album\nA,B\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_confidence() {
+        let entry = split_label("Daft Punk - One More Time");
+        assert_eq!(confidence(&entry, "Daft Punk", "One More Time"), 100);
+        assert_eq!(confidence(&entry, "Daft Punk", "Around the World"), 40);
+        assert_eq!(confidence(&entry, "Someone", "Else"), 0);
+    }
+}
@@ -0,0 +1,374 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde_json::Value;
+
+/// Hidden folder in a library root that deleted playlists are moved to when no
+/// trash dir is configured. It is emptied when the app quits.
+pub const SESSION_TRASH: &str = ".spotia-trash";
+
+/// Archives the Manager lists next to the playlist folders.
+const ARCHIVES: [&str; 4] = ["zip", "rar", "tar", "7z"];
+
+/// The playlist folders and archives in the library root `dir`, leaving out
+/// hidden ones.
+pub fn entries(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut entries = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+            continue;
+        };
+        if name.starts_with('.') {
+            continue;
+        }
+        let archive = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| ARCHIVES.contains(&ext.to_lowercase().as_str()));
+        if path.is_dir() || archive {
+            entries.push(path);
+        }
+    }
+    Ok(entries)
+}
+
+/// A song listed in the `save.spotdl` file of a playlist folder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SavedSong {
+    /// Spotify id, or the lowercased title when the save file has none.
+    pub id: String,
+    pub name: String,
+    pub artist: String,
+    /// Length of the track on Spotify.
+    pub seconds: u64,
+    pub url: Option<String>,
+}
+
+impl SavedSong {
+    /// `artist - name`, or just the name when the artist is unknown.
+    pub fn title(&self) -> String {
+        if self.artist.is_empty() {
+            self.name.clone()
+        } else {
+            format!("{} - {}", self.artist, self.name)
+        }
+    }
+}
+
+/// The songs listed in the `save.spotdl` file of the playlist folder `dir`. A
+/// missing or unreadable file lists no songs.
+pub fn saved_songs(dir: &Path) -> Vec<SavedSong> {
+    let Some(save) = fs::read_to_string(dir.join("save.spotdl"))
+        .ok()
+        .and_then(|text| serde_json::from_str::<Value>(&text).ok())
+    else {
+        return vec![];
+    };
+    let songs = match &save {
+        Value::Array(songs) => songs,
+        Value::Object(save) => match save.get("songs") {
+            Some(Value::Array(songs)) => songs,
+            _ => return vec![],
+        },
+        _ => return vec![],
+    };
+    songs
+        .iter()
+        .filter_map(|song| {
+            let text = |key: &str| song.get(key).and_then(Value::as_str).map(str::to_string);
+            let mut song = SavedSong {
+                id: text("song_id").unwrap_or_default(),
+                name: text("name")?,
+                artist: text("artist").unwrap_or_default(),
+                seconds: song
+                    .get("duration")
+                    .and_then(Value::as_f64)
+                    .unwrap_or(0.0)
+                    .round() as u64,
+                url: text("url"),
+            };
+            if song.id.is_empty() {
+                song.id = song.title().to_lowercase();
+            }
+            Some(song)
+        })
+        .collect()
+}
+
+/// A change made to the playlist folders, kept so it can be undone.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    /// Renamed, or moved to another library root.
+    Moved {
+        from: PathBuf,
+        to: PathBuf,
+    },
+    /// Moved to the trash.
+    Deleted {
+        from: PathBuf,
+        trash: PathBuf,
+    },
+    Duplicated {
+        copy: PathBuf,
+    },
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Change::Moved { from, to } if from.parent() == to.parent() => {
+                write!(f, "renamed {} to {}", name(from), name(to))
+            }
+            Change::Moved { from, to } => {
+                write!(f, "moved {} to {}", name(from), display_parent(to))
+            }
+            Change::Deleted { from, .. } => write!(f, "deleted {}", name(from)),
+            Change::Duplicated { copy } => write!(f, "duplicated as {}", name(copy)),
+        }
+    }
+}
+
+impl Change {
+    /// Puts the folders back the way they were before the change.
+    pub fn undo(&self) -> io::Result<()> {
+        match self {
+            Change::Moved { from, to } => move_dir(to, from),
+            Change::Deleted { from, trash } => move_dir(trash, from),
+            Change::Duplicated { copy } => fs::remove_dir_all(copy),
+        }
+    }
+}
+
+/// Removes for good the playlists that were deleted to a session trash.
+pub fn empty_session_trash(changes: &[Change]) {
+    for change in changes {
+        let Change::Deleted { trash, .. } = change else {
+            continue;
+        };
+        let Some(dir) = trash.parent().filter(|dir| dir.ends_with(SESSION_TRASH)) else {
+            continue;
+        };
+        if let Err(err) = fs::remove_dir_all(trash) {
+            log::error!("Failed to empty {}: {}", trash.display(), err);
+        }
+        // Only goes away once it is empty
+        let _ = fs::remove_dir(dir);
+    }
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn display_parent(path: &Path) -> String {
+    path.parent()
+        .map(|parent| parent.display().to_string())
+        .unwrap_or_default()
+}
+
+/// Checks that `name` can be used as a playlist folder name.
+pub fn check_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("The name cannot be empty".to_string());
+    }
+    if name.contains('/') || name.contains('\\') {
+        return Err("The name cannot contain a path separator".to_string());
+    }
+    if name.starts_with('.') {
+        return Err("The name cannot start with a dot".to_string());
+    }
+    Ok(())
+}
+
+/// Renames the playlist folder at `from`. Everything inside moves along, so the
+/// `save.spotdl` file keeps pointing at the same playlist.
+pub fn rename(from: &Path, name: &str) -> io::Result<Change> {
+    check_name(name).map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let to = from.with_file_name(name);
+    move_dir(from, &to)?;
+    Ok(Change::Moved {
+        from: from.to_path_buf(),
+        to,
+    })
+}
+
+/// Moves the playlist folder at `from` into the library root `root`.
+pub fn move_to(from: &Path, root: &Path) -> io::Result<Change> {
+    let to = root.join(from.file_name().unwrap_or_default());
+    move_dir(from, &to)?;
+    Ok(Change::Moved {
+        from: from.to_path_buf(),
+        to,
+    })
+}
+
+/// Moves the playlist folder at `from` to `trash`, or to the session trash of its
+/// library root if no trash dir is given.
+pub fn delete(from: &Path, trash: Option<&Path>) -> io::Result<Change> {
+    let trash = match trash {
+        Some(trash) => trash.to_path_buf(),
+        None => from.parent().unwrap_or(Path::new(".")).join(SESSION_TRASH),
+    };
+    fs::create_dir_all(&trash)?;
+    // Prefixed so deleting two playlists of the same name keeps both
+    let stamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|time| time.as_millis())
+        .unwrap_or_default();
+    let to = unique_path(&trash, &format!("{}-{}", stamp, name(from)));
+    move_dir(from, &to)?;
+    Ok(Change::Deleted {
+        from: from.to_path_buf(),
+        trash: to,
+    })
+}
+
+/// Copies the playlist folder at `from` next to it, under the first free name of
+/// `<name> copy`, `<name> copy 2` and so on.
+pub fn duplicate(from: &Path) -> io::Result<Change> {
+    let parent = from.parent().unwrap_or(Path::new("."));
+    let copy = unique_path(parent, &format!("{} copy", name(from)));
+    copy_dir(from, &copy)?;
+    Ok(Change::Duplicated { copy })
+}
+
+/// `dir/name`, or `dir/name 2`, `dir/name 3`... if that is taken.
+fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    let mut path = dir.join(name);
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{} {}", name, n));
+        n += 1;
+    }
+    path
+}
+
+/// Moves a folder, copying it when it cannot be renamed in place because it
+/// goes to another file system. Never overwrites an existing folder.
+pub fn move_dir(from: &Path, to: &Path) -> io::Result<()> {
+    if to.exists() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("{} already exists", to.display()),
+        ));
+    }
+    match fs::rename(from, to) {
+        Err(err) if err.kind() == io::ErrorKind::CrossesDevices => {
+            copy_dir(from, to)?;
+            fs::remove_dir_all(from)
+        }
+        result => result,
+    }
+}
+
+fn copy_dir(from: &Path, to: &Path) -> io::Result<()> {
+    fs::create_dir(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::utils::testing::TestDir;
+
+    /// A library root holding one playlist folder with a `save.spotdl` file.
+    fn library(name: &str) -> io::Result<(TestDir, PathBuf)> {
+        let root = TestDir::new(name);
+        let playlist = root.join("Chill");
+        fs::create_dir_all(&playlist)?;
+        fs::write(playlist.join("save.spotdl"), b"{\"type\": \"sync\"}")?;
+        fs::write(playlist.join("song.mp3"), b"")?;
+        Ok((root, playlist))
+    }
+
+    #[test]
+    fn test_rename_keeps_save_file() -> io::Result<()> {
+        let (root, playlist) = library("rename")?;
+
+        let change = rename(&playlist, "Relax")?;
+        assert_eq!(change.to_string(), "renamed Chill to Relax");
+        assert!(root.join("Relax/save.spotdl").exists());
+        assert!(!playlist.exists());
+        assert!(rename(&root.join("Relax"), "a/b").is_err());
+
+        change.undo()?;
+        assert!(playlist.join("save.spotdl").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_delete_to_session_trash_and_undo() -> io::Result<()> {
+        let (root, playlist) = library("delete")?;
+
+        let change = delete(&playlist, None)?;
+        let Change::Deleted { trash, .. } = &change else {
+            panic!("unexpected change {:?}", change);
+        };
+        assert!(trash.starts_with(root.join(SESSION_TRASH)));
+        assert!(trash.join("save.spotdl").exists());
+        assert!(!playlist.exists());
+
+        change.undo()?;
+        assert!(playlist.join("song.mp3").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_and_move() -> io::Result<()> {
+        let (root, playlist) = library("duplicate")?;
+
+        let first = duplicate(&playlist)?;
+        let second = duplicate(&playlist)?;
+        assert_eq!(
+            second,
+            Change::Duplicated {
+                copy: root.join("Chill copy 2")
+            }
+        );
+        assert!(root.join("Chill copy/save.spotdl").exists());
+        second.undo()?;
+        first.undo()?;
+        assert!(!root.join("Chill copy").exists());
+
+        let other = root.join("other");
+        fs::create_dir_all(&other)?;
+        let moved = move_to(&playlist, &other)?;
+        assert!(other.join("Chill/song.mp3").exists());
+        // Moving back onto an existing folder is refused
+        fs::create_dir_all(&playlist)?;
+        assert!(moved.undo().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_move_dir_only_copies_across_file_systems() -> io::Result<()> {
+        let (root, playlist) = library("move-dir")?;
+
+        // A failed rename is reported as is, without copying anything
+        let to = root.join("missing/Chill");
+        assert_eq!(
+            move_dir(&playlist, &to).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+        assert!(playlist.join("song.mp3").exists());
+        assert!(!to.exists());
+        Ok(())
+    }
+}
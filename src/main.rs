@@ -4,20 +4,36 @@
 
 pub mod action;
 pub mod app;
+pub mod chord;
 pub mod cli;
 pub mod components;
 pub mod config;
+pub mod doctor;
+pub mod export;
+pub mod fuzzy;
+pub mod import;
+pub mod library;
+pub mod mirror;
 pub mod mode;
+pub mod mouse;
+pub mod screen;
+pub mod stats;
+pub mod transcode;
 pub mod tui;
 pub mod utils;
+pub mod verify;
+
+use std::path::{Path, PathBuf};
 
 use clap::Parser;
-use cli::Cli;
-use color_eyre::eyre::Result;
-use components::spotify::Spotify;
+use cli::{Cli, Commands, ConfigCommand};
+use color_eyre::eyre::{eyre, Result};
+use components::spotify::{resolve_credentials, resolve_oauth, Spotify};
 
 use crate::{
-    app::App,
+    app::{run_setup_wizard, App},
+    config::{check_config, Config, Profile},
+    export::ExportFormat,
     utils::{initialize_logging, initialize_panic_handler, version},
 };
 
@@ -27,13 +43,110 @@ async fn tokio_main() -> Result<()> {
     initialize_panic_handler()?;
 
     let args = Cli::parse();
-    let spotify = Spotify::new().await;
-    let mut app = App::new(args.tick_rate, args.frame_rate, spotify)?;
+    if let Some(Commands::Config {
+        command: ConfigCommand::Check,
+    }) = args.command
+    {
+        return run_config_check();
+    }
+    let (mut config, issues) = Config::load();
+    for issue in &issues {
+        log::warn!("{}", issue);
+    }
+    let mut profile = config.profile(&args.profile)?;
+    match args.command {
+        Some(Commands::Doctor { music_dir }) => {
+            let music_dir = music_dir.or_else(|| profile.music_dir.clone().map(PathBuf::from));
+            return run_doctor(&profile, music_dir.as_deref());
+        }
+        Some(Commands::Export {
+            format,
+            output,
+            playlists,
+        }) => {
+            let spotify = Spotify::new(&profile).await?;
+            return run_export(&spotify, format, &output, &playlists).await;
+        }
+        Some(Commands::Config { .. }) | None => {}
+    }
+
+    let settings = &profile.spotify;
+    if resolve_credentials(settings)?.is_none() || resolve_oauth(settings).is_none() {
+        match run_setup_wizard(args.tick_rate, args.frame_rate, &profile.name).await? {
+            Some(settings) => {
+                // Pick up the credentials file the wizard just wrote
+                config = Config::load().0;
+                profile.spotify = settings;
+            }
+            None => return Ok(()),
+        }
+    }
+    let spotify = Spotify::new(&profile).await?;
+    let mut app = App::new(
+        args.tick_rate,
+        args.frame_rate,
+        config,
+        profile,
+        spotify,
+        issues,
+    )?;
     app.run().await?;
 
     Ok(())
 }
 
+async fn run_export(
+    spotify: &Spotify,
+    format: ExportFormat,
+    output: &Path,
+    playlists: &[String],
+) -> Result<()> {
+    let idxs = if playlists.is_empty() {
+        (0..spotify.playlists.len()).collect()
+    } else {
+        playlists
+            .iter()
+            .map(|name| {
+                spotify
+                    .playlists
+                    .iter()
+                    .position(|p| &p.name == name)
+                    .ok_or_else(|| eyre!("No playlist named `{}`", name))
+            })
+            .collect::<Result<Vec<_>>>()?
+    };
+    let count = spotify.export_playlists(&idxs, format, output).await?;
+    println!("Exported {} tracks to {}", count, output.display());
+    Ok(())
+}
+
+fn run_config_check() -> Result<()> {
+    let issues = check_config();
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    if !issues.is_empty() {
+        eprintln!("{} problem(s) found", issues.len());
+        std::process::exit(libc::EXIT_FAILURE);
+    }
+    println!("Configuration OK");
+    Ok(())
+}
+
+fn run_doctor(profile: &Profile, music_dir: Option<&Path>) -> Result<()> {
+    let checks = doctor::run_checks(&profile.spotify, music_dir);
+    for check in &checks {
+        let status = if check.passed { "PASS" } else { "FAIL" };
+        println!("[{}] {:<20} {}", status, check.name, check.detail);
+    }
+    let failed = checks.iter().filter(|check| !check.passed).count();
+    if failed > 0 {
+        eprintln!("{} check(s) failed", failed);
+        std::process::exit(libc::EXIT_FAILURE);
+    }
+    Ok(())
+}
+
 #[tokio::main]
 async fn main() -> Result<()> {
     if let Err(e) = tokio_main().await {
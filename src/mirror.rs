@@ -0,0 +1,497 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    env, fs, io,
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    doctor::free_space,
+    transcode::{audio_files, hash_file, Format, Transcoder},
+};
+
+/// File in the target folder listing the files the mirror put there. Only those
+/// are ever replaced or removed, anything else on the device is left alone.
+pub const MANIFEST: &str = ".spotia-mirror.json";
+
+/// Largest difference between two modification times still taken as equal, as
+/// FAT file systems only keep them to two seconds.
+const TIME_SLACK: u64 = 2;
+
+/// How the mirror decides that a file changed since it was last copied.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Compare {
+    /// Size and modification time, like rsync.
+    #[default]
+    Time,
+    /// Size and a hash of the content, slower but not fooled by touched files.
+    Hash,
+}
+
+/// Settings of one mirror run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Options {
+    /// Most bytes the mirrored files may take up.
+    pub budget: Option<u64>,
+    /// Transcode to this format to fit more on the device.
+    pub format: Option<Format>,
+    pub compare: Compare,
+}
+
+/// What a mirrored file was made from, to tell whether it is still up to date.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Entry {
+    size: u64,
+    modified: u64,
+    hash: Option<String>,
+    format: Option<Format>,
+    /// Size of the file on the device.
+    stored: u64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Manifest {
+    files: BTreeMap<String, Entry>,
+}
+
+/// A file to write to the device.
+#[derive(Debug)]
+struct Job {
+    source: PathBuf,
+    target: String,
+    entry: Entry,
+    transcode: bool,
+}
+
+/// Counts of what a mirror run did.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Summary {
+    pub copied: usize,
+    pub kept: usize,
+    pub removed: usize,
+    /// Files left out because they did not fit in the budget, or because a file
+    /// the mirror did not make is in their place.
+    pub skipped: usize,
+    pub failed: usize,
+    /// Space taken up by the mirrored files afterwards.
+    pub bytes: u64,
+}
+
+/// Mirrors the playlist folders `playlists` into `target`: copies new and
+/// changed audio files, removes the ones copied before that are no longer part
+/// of the selection and leaves out what does not fit in the budget. Files are
+/// transcoded with `transcoder` when the options ask for it. Progress is
+/// reported through `log`.
+pub fn sync(
+    playlists: &[PathBuf],
+    target: &Path,
+    options: Options,
+    transcoder: &Transcoder,
+    log: &mut dyn FnMut(String),
+) -> io::Result<Summary> {
+    fs::create_dir_all(target)?;
+    let mut manifest = read_manifest(target)?;
+    let mut summary = Summary::default();
+
+    let stored: u64 = manifest.files.values().map(|entry| entry.stored).sum();
+    let budget = match (options.budget, free_space(target)) {
+        (Some(budget), Some(free)) => Some(budget.min(free + stored)),
+        (budget, free) => budget.or(free.map(|free| free + stored)),
+    };
+
+    // Everything wanted on the device, in playlist order
+    let mut wanted = HashSet::new();
+    let mut jobs = vec![];
+    for playlist in playlists {
+        let name = file_name(playlist);
+        for source in audio_files(playlist)? {
+            let format = options
+                .format
+                .filter(|format| format.needs_transcode(&source));
+            let transcode = format.is_some();
+            let relative = source.strip_prefix(playlist).unwrap_or(&source);
+            let mut target = Path::new(&name).join(relative);
+            if let Some(format) = format {
+                target.set_extension(format.codec.extension());
+            }
+            let target = target.to_string_lossy().replace('\\', "/");
+            if !wanted.insert(target.clone()) {
+                continue;
+            }
+            let entry = fingerprint(&source, options, transcode)?;
+            jobs.push(Job {
+                source,
+                target,
+                entry,
+                transcode,
+            });
+        }
+    }
+
+    for stale in manifest
+        .files
+        .keys()
+        .filter(|file| !wanted.contains(*file))
+        .cloned()
+        .collect::<Vec<_>>()
+    {
+        remove(target, &stale)?;
+        manifest.files.remove(&stale);
+        log(format!("Removed {}", stale));
+        summary.removed += 1;
+    }
+
+    let jobs = jobs
+        .into_iter()
+        .map(|job| {
+            let path = target.join(&job.target);
+            let current = manifest
+                .files
+                .get(&job.target)
+                .filter(|entry| up_to_date(entry, &job.entry, &path))
+                .cloned()
+                .or_else(|| adopt(&job, &path, options));
+            (job, current)
+        })
+        .collect::<Vec<_>>();
+
+    // Everything to transcode goes through the transcoder at once, so it can
+    // work on several files in parallel
+    let mut transcoded = HashMap::new();
+    if let Some(format) = options.format {
+        let sources = jobs
+            .iter()
+            .filter(|(job, current)| job.transcode && current.is_none())
+            .map(|(job, _)| job.source.clone())
+            .collect::<Vec<_>>();
+        let results = transcoder.transcode_all(&sources, format, log);
+        transcoded = sources.into_iter().zip(results).collect();
+    }
+
+    let mut used = 0;
+    for (mut job, current) in jobs {
+        let path = target.join(&job.target);
+        let from = match transcoded.remove(&job.source) {
+            Some(Ok(cached)) => cached,
+            // Already reported, the file on the device stays as it was
+            Some(Err(_)) => {
+                summary.failed += 1;
+                continue;
+            }
+            None => job.source.clone(),
+        };
+        let size = match &current {
+            Some(entry) => entry.stored,
+            None => fs::metadata(&from).map_or(0, |meta| meta.len()),
+        };
+        if budget.is_some_and(|budget| used + size > budget) {
+            if manifest.files.remove(&job.target).is_some() {
+                remove(target, &job.target)?;
+            }
+            log(format!("Skipped {}: over the budget", job.target));
+            summary.skipped += 1;
+            continue;
+        }
+        if let Some(entry) = current {
+            used += entry.stored;
+            manifest.files.insert(job.target, entry);
+            summary.kept += 1;
+            continue;
+        }
+        if path.exists() && !manifest.files.contains_key(&job.target) {
+            log(format!(
+                "Skipped {}: another file is in the way",
+                job.target
+            ));
+            summary.skipped += 1;
+            continue;
+        }
+        match write(&from, job.entry.modified, &path) {
+            Ok(stored) => {
+                job.entry.stored = stored;
+                used += stored;
+                log(format!("Copied {}", job.target));
+                manifest.files.insert(job.target, job.entry);
+                summary.copied += 1;
+            }
+            Err(err) => {
+                log(format!("Failed to copy {}: {}", job.target, err));
+                manifest.files.remove(&job.target);
+                summary.failed += 1;
+            }
+        }
+    }
+
+    summary.bytes = used;
+    fs::write(
+        target.join(MANIFEST),
+        serde_json::to_string_pretty(&manifest)?,
+    )?;
+    Ok(summary)
+}
+
+/// Removable drives mounted in the usual places, for picking a target.
+pub fn mount_points() -> Vec<String> {
+    let user = env::var("USER").unwrap_or_default();
+    let roots = [
+        PathBuf::from("/media").join(&user),
+        PathBuf::from("/run/media").join(&user),
+        PathBuf::from("/Volumes"),
+    ];
+    let mut mounts = roots
+        .iter()
+        .flat_map(|root| fs::read_dir(root).into_iter().flatten().flatten())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .map(|path| path.display().to_string())
+        .collect::<Vec<_>>();
+    mounts.sort();
+    mounts
+}
+
+/// Parses a size like `512M`, `8G` or `16GB` into bytes, in powers of 1024.
+pub fn parse_size(size: &str) -> Result<u64, String> {
+    let size = size.trim();
+    let digits = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, unit) = size.split_at(digits);
+    let number = number
+        .parse::<f64>()
+        .map_err(|_| format!("`{}` is not a size", size))?;
+    let shift = match unit.trim().to_uppercase().trim_end_matches(['B', 'I']) {
+        "" => 0,
+        "K" => 10,
+        "M" => 20,
+        "G" => 30,
+        "T" => 40,
+        _ => return Err(format!("Unknown unit in `{}`", size)),
+    };
+    Ok((number * (1u64 << shift) as f64) as u64)
+}
+
+fn read_manifest(target: &Path) -> io::Result<Manifest> {
+    match fs::read_to_string(target.join(MANIFEST)) {
+        Ok(text) => Ok(serde_json::from_str(&text)?),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Manifest::default()),
+        Err(err) => Err(err),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn modified_secs(meta: &fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |time| time.as_secs())
+}
+
+fn fingerprint(source: &Path, options: Options, transcode: bool) -> io::Result<Entry> {
+    let meta = fs::metadata(source)?;
+    let hash = match options.compare {
+        Compare::Time => None,
+        Compare::Hash => Some(hash_file(source)?),
+    };
+    Ok(Entry {
+        size: meta.len(),
+        modified: modified_secs(&meta),
+        hash,
+        format: options.format.filter(|_| transcode),
+        stored: 0,
+    })
+}
+
+/// Whether the file at `path`, made from `entry`, still matches the source now
+/// described by `source`.
+fn up_to_date(entry: &Entry, source: &Entry, path: &Path) -> bool {
+    let same_source = match &source.hash {
+        Some(hash) => entry.size == source.size && entry.hash.as_ref() == Some(hash),
+        None => entry.size == source.size && entry.modified.abs_diff(source.modified) <= TIME_SLACK,
+    };
+    let intact = fs::metadata(path).is_ok_and(|meta| meta.len() == entry.stored);
+    same_source && entry.format == source.format && intact
+}
+
+/// Takes over a file already on the device that was not copied by the mirror,
+/// e.g. by hand, when it is the same as its source.
+fn adopt(job: &Job, path: &Path, options: Options) -> Option<Entry> {
+    if job.transcode {
+        return None;
+    }
+    let meta = fs::metadata(path).ok()?;
+    let same = match options.compare {
+        Compare::Time => {
+            meta.len() == job.entry.size
+                && modified_secs(&meta).abs_diff(job.entry.modified) <= TIME_SLACK
+        }
+        Compare::Hash => meta.len() == job.entry.size && hash_file(path).ok() == job.entry.hash,
+    };
+    same.then(|| Entry {
+        stored: meta.len(),
+        ..job.entry.clone()
+    })
+}
+
+/// Copies `from` to `path` through a temporary file, so an interrupted copy
+/// never looks complete, and gives it the modification time of its source.
+/// Returns its size.
+fn write(from: &Path, modified: u64, path: &Path) -> io::Result<u64> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let part = path.with_file_name(format!(".{}.part", file_name(path)));
+    fs::copy(from, &part)?;
+    fs::File::options()
+        .write(true)
+        .open(&part)?
+        .set_modified(UNIX_EPOCH + Duration::from_secs(modified))?;
+    fs::rename(&part, path)?;
+    Ok(fs::metadata(path)?.len())
+}
+
+/// Removes a mirrored file, along with its folder once that is empty.
+fn remove(target: &Path, file: &str) -> io::Result<()> {
+    let path = target.join(file);
+    match fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(err) if err.kind() == io::ErrorKind::NotFound => {}
+        Err(err) => return Err(err),
+    }
+    if let Some(parent) = path.parent().filter(|parent| *parent != target) {
+        let _ = fs::remove_dir(parent);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::transcode::Codec;
+    use crate::utils::testing::TestDir;
+
+    /// A music folder with two playlists, and the device folder to mirror to.
+    fn library(name: &str) -> io::Result<(TestDir, PathBuf, PathBuf)> {
+        let root = TestDir::new(name);
+        for (file, size) in [
+            ("Chill/a.mp3", 100),
+            ("Chill/b.mp3", 200),
+            ("Road/c.flac", 300),
+        ] {
+            let path = root.join("music").join(file);
+            fs::create_dir_all(path.parent().unwrap())?;
+            fs::write(path, vec![0; size])?;
+        }
+        fs::write(root.join("music/Chill/cover.jpg"), b"jpg")?;
+        let (music, device) = (root.join("music"), root.join("device"));
+        Ok((root, music, device))
+    }
+
+    fn sync_quietly(playlists: &[PathBuf], target: &Path, options: Options) -> io::Result<Summary> {
+        let cache = target.with_file_name("cache");
+        sync(
+            playlists,
+            target,
+            options,
+            &Transcoder::new(cache, 2),
+            &mut |_| {},
+        )
+    }
+
+    #[test]
+    fn test_sync_copies_changes_and_removes_dropped() -> io::Result<()> {
+        let (_root, music, device) = library("mirror")?;
+        let playlists = [music.join("Chill"), music.join("Road")];
+
+        let summary = sync_quietly(&playlists, &device, Options::default())?;
+        assert_eq!((summary.copied, summary.kept, summary.bytes), (3, 0, 600));
+        assert!(device.join("Road/c.flac").exists());
+        assert!(!device.join("Chill/cover.jpg").exists());
+
+        fs::write(music.join("Chill/a.mp3"), vec![1; 150])?;
+        let summary = sync_quietly(&playlists, &device, Options::default())?;
+        assert_eq!((summary.copied, summary.kept), (1, 2));
+        assert_eq!(fs::metadata(device.join("Chill/a.mp3"))?.len(), 150);
+
+        // Files put on the device by hand are left alone
+        fs::write(device.join("Road/notes.txt"), b"mine")?;
+        let summary = sync_quietly(&playlists[..1], &device, Options::default())?;
+        assert_eq!((summary.removed, summary.kept), (1, 2));
+        assert!(!device.join("Road/c.flac").exists());
+        assert!(device.join("Road/notes.txt").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_budget_and_hash() -> io::Result<()> {
+        let (_root, music, device) = library("mirror-budget")?;
+        let playlists = [music.join("Chill"), music.join("Road")];
+        let options = Options {
+            budget: Some(350),
+            compare: Compare::Hash,
+            ..Default::default()
+        };
+
+        let summary = sync_quietly(&playlists, &device, options)?;
+        assert_eq!(
+            (summary.copied, summary.skipped, summary.bytes),
+            (2, 1, 300)
+        );
+        assert!(!device.join("Road/c.flac").exists());
+
+        // Touching a file is not a change when comparing content
+        fs::File::options()
+            .write(true)
+            .open(music.join("Chill/a.mp3"))?
+            .set_modified(UNIX_EPOCH + Duration::from_secs(1000))?;
+        let summary = sync_quietly(&playlists, &device, options)?;
+        assert_eq!((summary.copied, summary.kept, summary.skipped), (0, 2, 1));
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_transcodes_through_cache() -> io::Result<()> {
+        let (_root, music, device) = library("mirror-transcode")?;
+        let playlists = [music.join("Chill"), music.join("Road")];
+        let format = Format {
+            codec: Codec::Opus,
+            bitrate: 96,
+        };
+        let transcoder = Transcoder::new(device.with_file_name("cache"), 2);
+        for source in audio_files(&playlists[0])? {
+            let cached = transcoder.cached_path(&source, format)?;
+            fs::create_dir_all(cached.parent().unwrap())?;
+            fs::write(cached, b"opus")?;
+        }
+
+        // Without ffmpeg only the cached files make it to the device
+        let options = Options {
+            format: Some(format),
+            ..Default::default()
+        };
+        let summary = sync_quietly(&playlists, &device, options)?;
+        assert_eq!((summary.copied, summary.failed, summary.bytes), (2, 1, 8));
+        assert_eq!(fs::read(device.join("Chill/b.opus"))?, b"opus");
+        assert!(!device.join("Road/c.opus").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Ok(512));
+        assert_eq!(parse_size("8G"), Ok(8 << 30));
+        assert_eq!(parse_size("16GB"), Ok(16 << 30));
+        assert_eq!(parse_size("1.5 MiB"), Ok(1536 * 1024));
+        assert!(parse_size("lots").is_err());
+        assert!(parse_size("3X").is_err());
+    }
+}
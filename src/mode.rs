@@ -5,7 +5,11 @@ pub enum Mode {
     #[default]
     Home,
     Input,
+    // Music folder picker
+    Folder,
     Waiting,
+    Error,
+    Help,
 
     // Download menu
     Downloader,
@@ -13,6 +17,18 @@ pub enum Mode {
     // Manager menu
     Manager,
 
+    // Import menu
+    Importer,
+
+    // Diagnostics screen
+    Diagnostics,
+
+    // Library statistics
+    Dashboard,
+
+    // Profile switcher
+    Profiles,
+
     // Outputs
     Idle,
     Downloading,
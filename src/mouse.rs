@@ -0,0 +1,119 @@
+use std::time::{Duration, Instant};
+
+use crossterm::event::{MouseButton, MouseEvent, MouseEventKind};
+use ratatui::layout::{Margin, Rect};
+
+/// Longest gap between the two clicks of a double-click.
+const DOUBLE_CLICK: Duration = Duration::from_millis(400);
+
+/// Whether `mouse` happened inside `area`.
+pub fn hit(area: Rect, mouse: &MouseEvent) -> bool {
+    (area.left()..area.right()).contains(&mouse.column)
+        && (area.top()..area.bottom()).contains(&mouse.row)
+}
+
+/// Index of the list entry under `mouse`, for a list whose entries are drawn one
+/// per row in `rows`, scrolled by `offset`.
+pub fn row_at(rows: Rect, offset: usize, len: usize, mouse: &MouseEvent) -> Option<usize> {
+    if !hit(rows, mouse) {
+        return None;
+    }
+    let index = offset + usize::from(mouse.row - rows.y);
+    (index < len).then_some(index)
+}
+
+/// The rows of a bordered list drawn in `area`, skipping `header` rows below the
+/// top border.
+pub fn list_rows(area: Rect, header: u16) -> Rect {
+    let inner = area.inner(&Margin::new(1, 1));
+    Rect {
+        y: inner.y + header.min(inner.height),
+        height: inner.height.saturating_sub(header),
+        ..inner
+    }
+}
+
+/// How many clicks in a row a left click completes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Click {
+    Single,
+    Double,
+}
+
+/// Tells double-clicks apart from single ones.
+#[derive(Debug, Default)]
+pub struct ClickTracker {
+    last: Option<(Instant, u16, u16)>,
+}
+
+impl ClickTracker {
+    /// Records a left click, returning `None` for any other mouse event.
+    pub fn click(&mut self, mouse: &MouseEvent, now: Instant) -> Option<Click> {
+        if mouse.kind != MouseEventKind::Down(MouseButton::Left) {
+            return None;
+        }
+        let double = self.last.is_some_and(|(at, column, row)| {
+            (column, row) == (mouse.column, mouse.row) && now.duration_since(at) <= DOUBLE_CLICK
+        });
+        if double {
+            // A third click starts over instead of making another double-click
+            self.last = None;
+            Some(Click::Double)
+        } else {
+            self.last = Some((now, mouse.column, mouse.row));
+            Some(Click::Single)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crossterm::event::KeyModifiers;
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    fn mouse(kind: MouseEventKind, column: u16, row: u16) -> MouseEvent {
+        MouseEvent {
+            kind,
+            column,
+            row,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    fn left(column: u16, row: u16) -> MouseEvent {
+        mouse(MouseEventKind::Down(MouseButton::Left), column, row)
+    }
+
+    #[test]
+    fn test_row_at() {
+        let rows = list_rows(Rect::new(0, 2, 20, 10), 1);
+        assert_eq!(rows, Rect::new(1, 4, 18, 7));
+
+        assert_eq!(row_at(rows, 0, 5, &left(3, 4)), Some(0));
+        assert_eq!(row_at(rows, 10, 15, &left(3, 6)), Some(12));
+        // Past the last entry, on the header and on the border
+        assert_eq!(row_at(rows, 0, 2, &left(3, 6)), None);
+        assert_eq!(row_at(rows, 0, 5, &left(3, 3)), None);
+        assert_eq!(row_at(rows, 0, 5, &left(0, 4)), None);
+    }
+
+    #[test]
+    fn test_double_click() {
+        let mut clicks = ClickTracker::default();
+        let now = Instant::now();
+        let later = |ms| now + Duration::from_millis(ms);
+
+        assert_eq!(clicks.click(&left(1, 1), now), Some(Click::Single));
+        assert_eq!(clicks.click(&left(1, 1), later(100)), Some(Click::Double));
+        assert_eq!(clicks.click(&left(1, 1), later(200)), Some(Click::Single));
+        // Too slow, then somewhere else
+        assert_eq!(clicks.click(&left(1, 1), later(1000)), Some(Click::Single));
+        assert_eq!(clicks.click(&left(2, 1), later(1100)), Some(Click::Single));
+        assert_eq!(
+            clicks.click(&mouse(MouseEventKind::ScrollDown, 2, 1), later(1200)),
+            None
+        );
+    }
+}
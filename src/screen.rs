@@ -0,0 +1,134 @@
+use strum::Display;
+
+use crate::{components::Component, mode::Mode};
+
+/// Name under which a component is registered with the app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Display)]
+pub enum ComponentId {
+    Home,
+    Fps,
+    FolderPicker,
+    Download,
+    Importer,
+    Diagnostics,
+    Dashboard,
+    Profiles,
+    Manager,
+    Spotify,
+}
+
+/// What the body of the screen shows in a mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Screen {
+    /// Component receiving the input, `None` to keep the one of the screen below.
+    pub focus: Option<ComponentId>,
+    /// Components drawn in the body, back to front.
+    pub components: &'static [ComponentId],
+}
+
+/// The layout of the screen shown in `mode`. Overlays like help or errors draw
+/// nothing themselves and leave the screen below them visible.
+pub fn screen(mode: Mode) -> Screen {
+    use ComponentId::*;
+    let (focus, components): (_, &'static [ComponentId]) = match mode {
+        Mode::Home => (Some(Home), &[Home, Fps]),
+        Mode::Downloader => (Some(Home), &[Home, Spotify, Fps]),
+        Mode::Manager => (Some(Home), &[Home, Manager, Fps]),
+        Mode::Downloading | Mode::Waiting => (Some(Download), &[Download, Fps]),
+        Mode::Importer => (Some(Importer), &[Importer, Fps]),
+        Mode::Diagnostics => (Some(Diagnostics), &[Diagnostics, Fps]),
+        Mode::Dashboard => (Some(Dashboard), &[Dashboard, Fps]),
+        Mode::Profiles => (Some(Profiles), &[Profiles, Fps]),
+        Mode::Folder => (Some(FolderPicker), &[FolderPicker, Fps]),
+        // Text input goes to whichever component asked for it
+        Mode::Input | Mode::Error | Mode::Help | Mode::Idle => (None, &[]),
+    };
+    Screen { focus, components }
+}
+
+/// Every component of the app by id, along with the stack of components that
+/// hold the focus.
+#[derive(Default)]
+pub struct Registry {
+    components: Vec<(ComponentId, Box<dyn Component>)>,
+    focus: Vec<Option<ComponentId>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `component` under `id`, replacing any component already registered
+    /// under it.
+    pub fn register(&mut self, id: ComponentId, component: Box<dyn Component>) {
+        match self.components.iter_mut().find(|(other, _)| *other == id) {
+            Some((_, slot)) => *slot = component,
+            None => self.components.push((id, component)),
+        }
+    }
+
+    pub fn get_mut(&mut self, id: ComponentId) -> Option<&mut Box<dyn Component>> {
+        self.components
+            .iter_mut()
+            .find(|(other, _)| *other == id)
+            .map(|(_, component)| component)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut Box<dyn Component>> {
+        self.components.iter_mut().map(|(_, component)| component)
+    }
+
+    /// The component receiving input.
+    pub fn focused(&self) -> Option<ComponentId> {
+        self.focus.iter().rev().find_map(|id| *id)
+    }
+
+    /// Gives the focus to `id` underneath any overlay.
+    pub fn focus_base(&mut self, id: Option<ComponentId>) {
+        match self.focus.first_mut() {
+            Some(base) => *base = id.or(*base),
+            None => self.focus.push(id),
+        }
+    }
+
+    /// Gives the focus to `id` until the matching `pop_focus`, `None` leaving it
+    /// where it is.
+    pub fn push_focus(&mut self, id: Option<ComponentId>) {
+        self.focus.push(id);
+    }
+
+    pub fn pop_focus(&mut self) {
+        if self.focus.len() > 1 {
+            self.focus.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+
+    #[test]
+    fn test_focus_stack() {
+        let mut registry = Registry::new();
+        assert_eq!(registry.focused(), None);
+
+        registry.focus_base(Some(ComponentId::Home));
+        registry.push_focus(None);
+        assert_eq!(registry.focused(), Some(ComponentId::Home));
+
+        // The base changes underneath an overlay that did not take the focus
+        registry.focus_base(Some(ComponentId::Download));
+        assert_eq!(registry.focused(), Some(ComponentId::Download));
+
+        registry.push_focus(Some(ComponentId::Profiles));
+        assert_eq!(registry.focused(), Some(ComponentId::Profiles));
+        registry.pop_focus();
+        registry.pop_focus();
+        registry.pop_focus();
+        assert_eq!(registry.focused(), Some(ComponentId::Download));
+    }
+}
@@ -0,0 +1,246 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs, io,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use crate::library;
+
+/// Extensions of the files counted as tracks.
+pub const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "m4a", "flac", "opus", "ogg", "wav", "aac"];
+
+/// Common encoder bitrates in kbps, estimates are rounded to the closest one.
+const BITRATES: [u64; 7] = [64, 96, 128, 160, 192, 256, 320];
+
+/// Days covered by the download history.
+pub const HISTORY_DAYS: usize = 30;
+
+const DAY: u64 = 24 * 60 * 60;
+
+/// Figures for one playlist folder.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PlaylistStats {
+    pub name: String,
+    pub tracks: usize,
+    pub bytes: u64,
+    /// Total length of the songs listed in `save.spotdl`.
+    pub seconds: u64,
+    /// Average bitrate estimated from the size and length, in kbps.
+    pub bitrate: Option<u64>,
+}
+
+/// A song found in more than one playlist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Duplicate {
+    pub title: String,
+    pub playlists: usize,
+}
+
+/// Summary of the playlist folders of a music directory.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LibraryStats {
+    pub playlists: Vec<PlaylistStats>,
+    /// Track count per file extension.
+    pub formats: BTreeMap<String, u64>,
+    /// Track count per estimated bitrate, in kbps.
+    pub bitrates: BTreeMap<u64, u64>,
+    /// Most duplicated songs first.
+    pub duplicates: Vec<Duplicate>,
+    /// Tracks downloaded on each of the last `HISTORY_DAYS` days, oldest first.
+    pub history: Vec<u64>,
+}
+
+impl LibraryStats {
+    /// Scans the playlist folders the Manager lists in `dir`.
+    pub fn scan(dir: &Path, now: SystemTime) -> io::Result<Self> {
+        let mut stats = Self {
+            history: vec![0; HISTORY_DAYS],
+            ..Default::default()
+        };
+        // Song to the number of playlists it is in
+        let mut songs: HashMap<String, (String, usize)> = HashMap::new();
+        for path in library::entries(dir)?
+            .into_iter()
+            .filter(|path| path.is_dir())
+        {
+            let mut playlist = PlaylistStats {
+                name: path
+                    .file_name()
+                    .map(|name| name.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                ..Default::default()
+            };
+            let mut formats: BTreeMap<String, u64> = BTreeMap::new();
+            for (ext, meta) in audio_files(&path) {
+                playlist.tracks += 1;
+                playlist.bytes += meta.len();
+                *formats.entry(ext).or_default() += 1;
+                if let Some(day) = meta.modified().ok().and_then(|time| days_ago(now, time)) {
+                    if day < HISTORY_DAYS {
+                        stats.history[HISTORY_DAYS - 1 - day] += 1;
+                    }
+                }
+            }
+            for song in library::saved_songs(&path) {
+                playlist.seconds += song.seconds;
+                let title = song.title();
+                songs.entry(song.id).or_insert((title, 0)).1 += 1;
+            }
+            playlist.bitrate = estimate_bitrate(playlist.bytes, playlist.seconds);
+            for (ext, count) in formats {
+                *stats.formats.entry(ext).or_default() += count;
+            }
+            if let Some(bitrate) = playlist.bitrate {
+                *stats.bitrates.entry(bitrate).or_default() += playlist.tracks as u64;
+            }
+            stats.playlists.push(playlist);
+        }
+        stats
+            .playlists
+            .sort_by(|a, b| b.bytes.cmp(&a.bytes).then(a.name.cmp(&b.name)));
+        stats.duplicates = songs
+            .into_values()
+            .filter(|(_, playlists)| *playlists > 1)
+            .map(|(title, playlists)| Duplicate { title, playlists })
+            .collect();
+        stats
+            .duplicates
+            .sort_by(|a, b| b.playlists.cmp(&a.playlists).then(a.title.cmp(&b.title)));
+        Ok(stats)
+    }
+
+    pub fn tracks(&self) -> usize {
+        self.playlists.iter().map(|playlist| playlist.tracks).sum()
+    }
+
+    pub fn bytes(&self) -> u64 {
+        self.playlists.iter().map(|playlist| playlist.bytes).sum()
+    }
+
+    pub fn seconds(&self) -> u64 {
+        self.playlists.iter().map(|playlist| playlist.seconds).sum()
+    }
+}
+
+/// Extension and metadata of every audio file under `dir`.
+fn audio_files(dir: &Path) -> Vec<(String, fs::Metadata)> {
+    let mut files = vec![];
+    let Ok(entries) = fs::read_dir(dir) else {
+        return files;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Ok(meta) = entry.metadata() else {
+            continue;
+        };
+        if meta.is_dir() {
+            files.extend(audio_files(&path));
+            continue;
+        }
+        let ext = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_lowercase)
+            .unwrap_or_default();
+        if AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+            files.push((ext, meta));
+        }
+    }
+    files
+}
+
+/// Average bitrate of `bytes` of audio lasting `seconds`, rounded to the closest
+/// common encoder bitrate.
+pub fn estimate_bitrate(bytes: u64, seconds: u64) -> Option<u64> {
+    if bytes == 0 || seconds == 0 {
+        return None;
+    }
+    let kbps = bytes * 8 / seconds / 1000;
+    BITRATES
+        .iter()
+        .copied()
+        .min_by_key(|bitrate| bitrate.abs_diff(kbps))
+}
+
+/// Whole days between `time` and `now`, `None` if `time` is in the future.
+fn days_ago(now: SystemTime, time: SystemTime) -> Option<usize> {
+    now.duration_since(time)
+        .ok()
+        .map(|age| (age.as_secs() / DAY) as usize)
+}
+
+/// Length formatted as hours and minutes, e.g. `12h 05m`.
+pub fn format_duration(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    format!("{}h {:02}m", minutes / 60, minutes % 60)
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use serde_json::json;
+
+    use super::*;
+    use crate::utils::testing::TestDir;
+
+    fn playlist(root: &Path, name: &str, songs: &[(&str, &str, u64)], files: &[(&str, usize)]) {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        let songs = songs
+            .iter()
+            .map(|(id, name, duration)| {
+                json!({"song_id": id, "name": name, "artist": "Band", "duration": duration})
+            })
+            .collect::<Vec<_>>();
+        let save = json!({"type": "sync", "query": [], "songs": songs});
+        fs::write(dir.join("save.spotdl"), save.to_string()).unwrap();
+        for (file, size) in files {
+            fs::write(dir.join(file), vec![0; *size]).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_scan() -> io::Result<()> {
+        let root = TestDir::new("stats");
+        // 16000 bytes over 1 second is 128 kbps
+        playlist(
+            &root,
+            "Chill",
+            &[("a", "Calm", 1), ("b", "Slow", 1)],
+            &[("Calm.mp3", 16000), ("Slow.mp3", 16000), ("cover.jpg", 10)],
+        );
+        playlist(&root, "Road", &[("a", "Calm", 2)], &[("Calm.flac", 80000)]);
+        fs::write(root.join("Old.zip"), b"")?;
+
+        let stats = LibraryStats::scan(&root, SystemTime::now())?;
+        assert_eq!(stats.tracks(), 3);
+        assert_eq!(stats.bytes(), 112000);
+        assert_eq!(stats.seconds(), 4);
+        assert_eq!(stats.playlists[0].name, "Road");
+        assert_eq!(stats.playlists[1].bitrate, Some(128));
+        assert_eq!(stats.playlists[0].bitrate, Some(320));
+        assert_eq!(
+            stats.formats,
+            BTreeMap::from([("flac".to_string(), 1), ("mp3".to_string(), 2)])
+        );
+        assert_eq!(stats.bitrates, BTreeMap::from([(128, 2), (320, 1)]));
+        assert_eq!(
+            stats.duplicates,
+            vec![Duplicate {
+                title: "Band - Calm".to_string(),
+                playlists: 2
+            }]
+        );
+        assert_eq!(stats.history[HISTORY_DAYS - 1], 3);
+        Ok(())
+    }
+
+    #[test]
+    fn test_estimate_bitrate() {
+        assert_eq!(estimate_bitrate(0, 10), None);
+        assert_eq!(estimate_bitrate(10, 0), None);
+        assert_eq!(estimate_bitrate(24_000 * 10, 10), Some(192));
+        assert_eq!(estimate_bitrate(30_000 * 10, 10), Some(256));
+    }
+}
@@ -0,0 +1,598 @@
+use std::{
+    collections::HashMap,
+    fmt, fs,
+    io::{self, BufRead, BufReader},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{stats::AUDIO_EXTENSIONS, verify};
+
+/// Folder in the data dir transcoded files are cached in.
+pub const CACHE_DIR: &str = "transcode-cache";
+
+/// Most space the cache takes up unless configured otherwise.
+pub const DEFAULT_CACHE_SIZE: u64 = 2 << 30;
+
+/// Folder of each library root transcoded playlists are written to when no
+/// transcode dir is configured. Being hidden, it is not listed as a playlist.
+pub const TRANSCODED_DIR: &str = ".spotia-transcoded";
+
+/// File in the cache dir remembering the content hash of the sources, so a
+/// source only has to be read again once it changed.
+const INDEX: &str = "index.json";
+
+/// Folder of the output dir exports are put together in before being zipped.
+const EXPORT_STAGING: &str = ".export";
+
+/// Numbers the temporary files ffmpeg writes to, so two sources with the same
+/// content, transcoded at the same time, do not write to the same file.
+static NEXT_PART: AtomicUsize = AtomicUsize::new(0);
+
+/// Audio codec files are transcoded to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    #[default]
+    Mp3,
+    /// AAC in an M4A file.
+    Aac,
+    /// Opus in an Ogg file. Ogg cannot hold cover art, so only tags are kept.
+    Opus,
+    /// Vorbis in an Ogg file, keeping tags only like Opus.
+    Vorbis,
+}
+
+impl Codec {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Codec::Mp3 => "mp3",
+            Codec::Aac => "m4a",
+            Codec::Opus => "opus",
+            Codec::Vorbis => "ogg",
+        }
+    }
+
+    fn encoder(&self) -> &'static str {
+        match self {
+            Codec::Mp3 => "libmp3lame",
+            Codec::Aac => "aac",
+            Codec::Opus => "libopus",
+            Codec::Vorbis => "libvorbis",
+        }
+    }
+
+    /// ffmpeg muxer, given explicitly as the files are written under a
+    /// temporary name.
+    fn muxer(&self) -> &'static str {
+        match self {
+            Codec::Mp3 => "mp3",
+            Codec::Aac => "ipod",
+            Codec::Opus => "opus",
+            Codec::Vorbis => "ogg",
+        }
+    }
+
+    fn keeps_cover(&self) -> bool {
+        matches!(self, Codec::Mp3 | Codec::Aac)
+    }
+}
+
+/// Codec and bitrate files are transcoded to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Format {
+    pub codec: Codec,
+    /// In kbps.
+    pub bitrate: u32,
+}
+
+impl Default for Format {
+    fn default() -> Self {
+        Self {
+            codec: Codec::Mp3,
+            bitrate: 192,
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}k", self.codec.extension(), self.bitrate)
+    }
+}
+
+impl Format {
+    /// Whether `source` has to be transcoded to end up in this format: anything
+    /// but files of the same codec that are already small enough.
+    pub fn needs_transcode(&self, source: &Path) -> bool {
+        let same_codec = source
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case(self.codec.extension()));
+        if !same_codec {
+            return true;
+        }
+        match (fs::metadata(source), verify::probe(source)) {
+            (Ok(meta), Ok(length)) if length.as_secs() > 0 => {
+                meta.len() * 8 / length.as_secs() / 1000 > self.bitrate as u64
+            }
+            _ => false,
+        }
+    }
+}
+
+/// Content hash of a source file as of its size and modification time.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct Hashed {
+    size: u64,
+    /// Nanoseconds since the epoch.
+    modified: u64,
+    hash: String,
+}
+
+/// Converts audio files with ffmpeg, several at a time, keeping their tags and
+/// cover art. Results are cached by the hash of the source, so converting the
+/// same file to the same format again is only a lookup.
+#[derive(Debug, Clone)]
+pub struct Transcoder {
+    cache_dir: PathBuf,
+    jobs: usize,
+    /// Size cap of the cache in bytes, the least recently used files go first.
+    max_size: Option<u64>,
+    /// Content hashes by canonical source path, shared between the workers.
+    index: Arc<Mutex<HashMap<PathBuf, Hashed>>>,
+}
+
+impl Transcoder {
+    /// A transcoder caching into `cache_dir` and running up to `jobs` ffmpeg
+    /// processes at once.
+    pub fn new(cache_dir: PathBuf, jobs: usize) -> Self {
+        let index = fs::read_to_string(cache_dir.join(INDEX))
+            .ok()
+            .and_then(|text| serde_json::from_str(&text).ok())
+            .unwrap_or_default();
+        Self {
+            cache_dir,
+            jobs: jobs.max(1),
+            max_size: Some(DEFAULT_CACHE_SIZE),
+            index: Arc::new(Mutex::new(index)),
+        }
+    }
+
+    pub fn max_size(mut self, bytes: u64) -> Self {
+        self.max_size = Some(bytes);
+        self
+    }
+
+    /// Where the transcoded copy of `source` in `format` is cached.
+    pub fn cached_path(&self, source: &Path, format: Format) -> io::Result<PathBuf> {
+        Ok(self.cache_dir.join(format!(
+            "{}-{}k.{}",
+            self.content_hash(source)?,
+            format.bitrate,
+            format.codec.extension()
+        )))
+    }
+
+    /// Hash of the content of `source`, only read again when its size or
+    /// modification time changed since it was last hashed.
+    fn content_hash(&self, source: &Path) -> io::Result<String> {
+        let meta = fs::metadata(source)?;
+        let modified = meta
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |time| time.as_nanos() as u64);
+        let key = fs::canonicalize(source)?;
+        let index = self.index.lock().unwrap_or_else(|err| err.into_inner());
+        if let Some(hashed) = index.get(&key) {
+            if hashed.size == meta.len() && hashed.modified == modified {
+                return Ok(hashed.hash.clone());
+            }
+        }
+        // Not holding the lock while reading the file
+        drop(index);
+        let hash = hash_file(source)?;
+        let hashed = Hashed {
+            size: meta.len(),
+            modified,
+            hash: hash.clone(),
+        };
+        self.index
+            .lock()
+            .unwrap_or_else(|err| err.into_inner())
+            .insert(key, hashed);
+        Ok(hash)
+    }
+
+    /// Removes the least recently used files from the cache until it fits in its
+    /// size cap. Returns how many were removed.
+    pub fn prune(&self) -> io::Result<usize> {
+        let Some(max_size) = self.max_size else {
+            return Ok(0);
+        };
+        let entries = match fs::read_dir(&self.cache_dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(0),
+            Err(err) => return Err(err),
+        };
+        let mut files = vec![];
+        for entry in entries {
+            let entry = entry?;
+            let meta = entry.metadata()?;
+            if meta.is_file() && entry.file_name() != INDEX {
+                files.push((meta.modified()?, meta.len(), entry.path()));
+            }
+        }
+        files.sort();
+        let mut size: u64 = files.iter().map(|(_, len, _)| len).sum();
+        let mut removed = 0;
+        for (_, len, path) in files {
+            if size <= max_size {
+                break;
+            }
+            fs::remove_file(path)?;
+            size -= len;
+            removed += 1;
+        }
+        Ok(removed)
+    }
+
+    /// Writes the content hashes to the cache dir, leaving out sources that are
+    /// gone.
+    fn save_index(&self) -> io::Result<()> {
+        let mut index = self.index.lock().unwrap_or_else(|err| err.into_inner());
+        index.retain(|path, _| path.exists());
+        fs::create_dir_all(&self.cache_dir)?;
+        fs::write(self.cache_dir.join(INDEX), serde_json::to_string(&*index)?)
+    }
+
+    /// Transcodes `source` to `format` unless it is cached already. Returns the
+    /// path of the result and whether it came from the cache.
+    pub fn transcode(&self, source: &Path, format: Format) -> io::Result<(PathBuf, bool)> {
+        let cached = self.cached_path(source, format)?;
+        if cached.exists() {
+            // Marks it as recently used for pruning
+            let _ = fs::File::options()
+                .write(true)
+                .open(&cached)
+                .and_then(|file| file.set_modified(SystemTime::now()));
+            return Ok((cached, true));
+        }
+        fs::create_dir_all(&self.cache_dir)?;
+        let part = cached.with_extension(format!(
+            "{}.part",
+            NEXT_PART.fetch_add(1, Ordering::Relaxed)
+        ));
+        let mut command = Command::new("ffmpeg");
+        command
+            .args(["-y", "-loglevel", "error", "-i"])
+            .arg(source)
+            .args(["-map", "0:a", "-map_metadata", "0"]);
+        if format.codec.keeps_cover() {
+            command.args([
+                "-map",
+                "0:v?",
+                "-c:v",
+                "copy",
+                "-disposition:v",
+                "attached_pic",
+            ]);
+        }
+        if format.codec == Codec::Mp3 {
+            command.args(["-id3v2_version", "3"]);
+        }
+        let output = command
+            .args(["-c:a", format.codec.encoder()])
+            .args(["-b:a", &format!("{}k", format.bitrate)])
+            .args(["-f", format.codec.muxer()])
+            .arg(&part)
+            .output()?;
+        if !output.status.success() {
+            let _ = fs::remove_file(&part);
+            return Err(io::Error::other(
+                String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            ));
+        }
+        fs::rename(&part, &cached)?;
+        Ok((cached, false))
+    }
+
+    /// Transcodes all of `sources` to `format`, spread over the workers,
+    /// reporting each file through `log` as it is done. The results are in the
+    /// order of `sources`.
+    pub fn transcode_all(
+        &self,
+        sources: &[PathBuf],
+        format: Format,
+        log: &mut dyn FnMut(String),
+    ) -> Vec<io::Result<PathBuf>> {
+        let total = sources.len();
+        let next = AtomicUsize::new(0);
+        let (tx, rx) = mpsc::channel();
+        let mut results = (0..total).map(|_| None).collect::<Vec<_>>();
+        thread::scope(|scope| {
+            for _ in 0..self.jobs.min(total) {
+                let tx = tx.clone();
+                let next = &next;
+                scope.spawn(move || loop {
+                    let i = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(source) = sources.get(i) else {
+                        break;
+                    };
+                    if tx.send((i, self.transcode(source, format))).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop(tx);
+            for (done, (i, result)) in rx.iter().enumerate() {
+                let name = sources[i].file_name().unwrap_or_default().to_string_lossy();
+                log(match &result {
+                    Ok((_, true)) => format!("[{}/{}] Cached {}", done + 1, total, name),
+                    Ok((_, false)) => format!("[{}/{}] Transcoded {}", done + 1, total, name),
+                    Err(err) => format!(
+                        "[{}/{}] Failed to transcode {}: {}",
+                        done + 1,
+                        total,
+                        name,
+                        err
+                    ),
+                });
+                results[i] = Some(result.map(|(path, _)| path));
+            }
+        });
+        if let Err(err) = self.save_index() {
+            log(format!("Failed to save the transcode cache index: {}", err));
+        }
+        results
+            .into_iter()
+            .map(|result| result.unwrap_or_else(|| Err(io::Error::other("not transcoded"))))
+            .collect()
+    }
+}
+
+/// Writes a transcoded copy of the playlist folder `dir` into `out_dir`, named
+/// after the format, e.g. `Chill (mp3 128k)`. Returns the new folder and how
+/// many files could not be transcoded.
+pub fn convert_playlist(
+    dir: &Path,
+    out_dir: &Path,
+    format: Format,
+    transcoder: &Transcoder,
+    log: &mut dyn FnMut(String),
+) -> io::Result<(PathBuf, usize)> {
+    let target = out_dir.join(converted_name(dir, format));
+    let failed = write_converted(dir, &target, format, transcoder, log)?;
+    Ok((target, failed))
+}
+
+/// Zips a transcoded copy of the playlist folder `dir` into `out_dir`, e.g.
+/// `Chill (mp3 128k).zip`, replacing an older archive of the same name. Returns
+/// the archive and how many files could not be transcoded.
+pub fn export_playlist(
+    dir: &Path,
+    out_dir: &Path,
+    format: Format,
+    transcoder: &Transcoder,
+    log: &mut dyn FnMut(String),
+) -> io::Result<(PathBuf, usize)> {
+    let name = converted_name(dir, format);
+    let archive = out_dir.join(format!("{}.zip", name));
+    // Staged apart so a transcoded folder of the same name is left alone
+    let staging = out_dir.join(EXPORT_STAGING);
+    if staging.exists() {
+        fs::remove_dir_all(&staging)?;
+    }
+    fs::create_dir_all(staging.join(&name))?;
+    let failed = write_converted(dir, &staging.join(&name), format, transcoder, log)?;
+    if archive.exists() {
+        fs::remove_file(&archive)?;
+    }
+    let output = Command::new("zip")
+        .current_dir(&staging)
+        .arg("-rq")
+        .arg(&archive)
+        .arg(&name)
+        .output();
+    fs::remove_dir_all(&staging)?;
+    let output = output?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+    Ok((archive, failed))
+}
+
+/// Name of the transcoded copy of the playlist folder `dir`.
+fn converted_name(dir: &Path, format: Format) -> String {
+    let name = dir.file_name().unwrap_or_default().to_string_lossy();
+    format!("{} ({})", name, format)
+}
+
+/// Writes the audio files of `dir`, transcoded, into `target`. Returns how many
+/// could not be transcoded.
+fn write_converted(
+    dir: &Path,
+    target: &Path,
+    format: Format,
+    transcoder: &Transcoder,
+    log: &mut dyn FnMut(String),
+) -> io::Result<usize> {
+    let sources = audio_files(dir)?;
+    let results = transcoder.transcode_all(&sources, format, log);
+    let mut failed = 0;
+    for (source, result) in sources.iter().zip(results) {
+        let Ok(cached) = result else {
+            failed += 1;
+            continue;
+        };
+        let relative = source.strip_prefix(dir).unwrap_or(source);
+        let path = target
+            .join(relative)
+            .with_extension(format.codec.extension());
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(cached, path)?;
+    }
+    Ok(failed)
+}
+
+/// Audio files under `dir`, sorted.
+pub fn audio_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut files = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            files.extend(audio_files(&path)?);
+        } else if path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+        {
+            files.push(path);
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// FNV-1a hash of the content of a file, read in chunks.
+pub fn hash_file(path: &Path) -> io::Result<String> {
+    let mut reader = BufReader::with_capacity(64 * 1024, fs::File::open(path)?);
+    let mut hash = 0xcbf29ce484222325u64;
+    loop {
+        let chunk = reader.fill_buf()?;
+        if chunk.is_empty() {
+            break;
+        }
+        hash = chunk.iter().fold(hash, |hash, &byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+        let read = chunk.len();
+        reader.consume(read);
+    }
+    Ok(format!("{:016x}", hash))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::utils::testing::TestDir;
+
+    #[test]
+    fn test_cached_results_skip_ffmpeg() -> io::Result<()> {
+        let root = TestDir::new("transcode");
+        let dir = root.join("Chill");
+        fs::create_dir_all(dir.join("disc 2"))?;
+        fs::write(dir.join("a.flac"), b"first")?;
+        fs::write(dir.join("disc 2/b.flac"), b"second")?;
+        fs::write(dir.join("cover.jpg"), b"jpg")?;
+
+        let transcoder = Transcoder::new(root.join("cache"), 4);
+        let format = Format {
+            codec: Codec::Opus,
+            bitrate: 96,
+        };
+        // Same content, same cache entry, wherever the file is
+        fs::write(root.join("copy.flac"), b"first")?;
+        assert_eq!(
+            transcoder.cached_path(&dir.join("a.flac"), format)?,
+            transcoder.cached_path(&root.join("copy.flac"), format)?
+        );
+        for source in audio_files(&dir)? {
+            let cached = transcoder.cached_path(&source, format)?;
+            fs::create_dir_all(cached.parent().unwrap())?;
+            fs::write(cached, b"opus")?;
+        }
+
+        let mut lines = vec![];
+        let out = root.join(TRANSCODED_DIR);
+        let (target, failed) = convert_playlist(&dir, &out, format, &transcoder, &mut |line| {
+            lines.push(line)
+        })?;
+        assert_eq!(target, out.join("Chill (opus 96k)"));
+        assert_eq!(failed, 0);
+        assert_eq!(fs::read(target.join("disc 2/b.opus"))?, b"opus");
+        assert!(!target.join("cover.jpg").exists());
+        assert_eq!(lines.len(), 2);
+        assert!(lines.iter().all(|line| line.contains("Cached")));
+        Ok(())
+    }
+
+    #[test]
+    fn test_prune_removes_least_recently_used() -> io::Result<()> {
+        let root = TestDir::new("transcode-prune");
+        let transcoder = Transcoder::new(root.to_path_buf(), 1).max_size(8);
+        for (name, secs) in [("old.mp3", 1000), ("new.mp3", 3000), ("mid.mp3", 2000)] {
+            fs::write(root.join(name), b"1234")?;
+            fs::File::options()
+                .write(true)
+                .open(root.join(name))?
+                .set_modified(UNIX_EPOCH + std::time::Duration::from_secs(secs))?;
+        }
+        fs::write(root.join(INDEX), b"{}")?;
+
+        assert_eq!(transcoder.prune()?, 1);
+        assert!(!root.join("old.mp3").exists());
+        assert!(root.join("mid.mp3").exists());
+        assert!(root.join(INDEX).exists());
+        assert_eq!(transcoder.prune()?, 0);
+        Ok(())
+    }
+
+    #[test]
+    fn test_content_hash_is_indexed() -> io::Result<()> {
+        let root = TestDir::new("transcode-index");
+        let source = root.join("a.flac");
+        fs::write(&source, b"a")?;
+        assert_eq!(hash_file(&source)?, "af63dc4c8601ec8c");
+
+        let transcoder = Transcoder::new(root.join("cache"), 1);
+        let first = transcoder.content_hash(&source)?;
+        transcoder.save_index()?;
+        // Same size and time: the file is not read again, even after a restart
+        let modified = fs::metadata(&source)?.modified()?;
+        fs::write(&source, b"b")?;
+        fs::File::options()
+            .write(true)
+            .open(&source)?
+            .set_modified(modified)?;
+        let reloaded = Transcoder::new(root.join("cache"), 1);
+        assert_eq!(reloaded.content_hash(&source)?, first);
+        // Any change to them and it is
+        fs::write(&source, b"bb")?;
+        assert_ne!(reloaded.content_hash(&source)?, first);
+        Ok(())
+    }
+
+    #[test]
+    fn test_needs_transcode() -> io::Result<()> {
+        let root = TestDir::new("needs-transcode");
+        // One second of 128 kbps MP3
+        let mut mp3 = vec![0xFF, 0xFB, 0x90, 0x00];
+        mp3.resize(16000, 0);
+        fs::write(root.join("song.mp3"), mp3)?;
+        fs::write(root.join("song.flac"), b"fLaC")?;
+
+        let mp3 = |bitrate| Format {
+            codec: Codec::Mp3,
+            bitrate,
+        };
+        assert!(!mp3(192).needs_transcode(&root.join("song.mp3")));
+        assert!(mp3(96).needs_transcode(&root.join("song.mp3")));
+        assert!(mp3(320).needs_transcode(&root.join("song.flac")));
+        Ok(())
+    }
+}
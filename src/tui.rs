@@ -93,6 +93,19 @@ impl Tui {
         self
     }
 
+    /// Turns mouse capture on or off while the terminal is in use.
+    pub fn set_mouse(&mut self, mouse: bool) -> Result<()> {
+        if mouse != self.mouse {
+            if mouse {
+                crossterm::execute!(io(), EnableMouseCapture)?;
+            } else {
+                crossterm::execute!(io(), DisableMouseCapture)?;
+            }
+            self.mouse = mouse;
+        }
+        Ok(())
+    }
+
     pub fn paste(mut self, paste: bool) -> Self {
         self.paste = paste;
         self
@@ -178,3 +178,45 @@ Config directory: {config_dir_path}
 Data directory: {data_dir_path}"
     )
 }
+
+#[cfg(test)]
+pub mod testing {
+    use std::{
+        fs,
+        ops::Deref,
+        path::{Path, PathBuf},
+    };
+
+    /// Scratch folder for a test under the system temp dir. It starts out empty
+    /// and is removed when dropped, even when an assertion fails first.
+    pub struct TestDir(PathBuf);
+
+    impl TestDir {
+        pub fn new(name: &str) -> Self {
+            let path = std::env::temp_dir().join(format!("spotia-test-{}", name));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).expect("failed to create the test dir");
+            Self(path)
+        }
+    }
+
+    impl Deref for TestDir {
+        type Target = Path;
+
+        fn deref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl AsRef<Path> for TestDir {
+        fn as_ref(&self) -> &Path {
+            &self.0
+        }
+    }
+
+    impl Drop for TestDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+}
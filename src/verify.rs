@@ -0,0 +1,582 @@
+use std::{
+    fmt, fs, io,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    library::{self, SavedSong},
+    stats::AUDIO_EXTENSIONS,
+};
+
+/// How far the length of a file may be off from its track before it is flagged.
+pub const DEFAULT_TOLERANCE: Duration = Duration::from_secs(5);
+
+/// How far into an MP3 file the first frame is looked for.
+const MP3_SYNC_WINDOW: usize = 64 * 1024;
+
+/// What is wrong with an audio file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Problem {
+    Unreadable(String),
+    Empty,
+    /// The file does not start the way files of its format do.
+    BadHeader,
+    /// The file ends before the length its header announces.
+    Truncated,
+    /// The header is fine but says nothing about the length.
+    NoDuration,
+    /// Lengths in seconds of the file and of its track on Spotify.
+    Mismatch {
+        actual: u64,
+        expected: u64,
+    },
+}
+
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Problem::Unreadable(err) => write!(f, "cannot be read: {}", err),
+            Problem::Empty => write!(f, "empty file"),
+            Problem::BadHeader => write!(f, "not a valid audio file"),
+            Problem::Truncated => write!(f, "cut off before the end"),
+            Problem::NoDuration => write!(f, "length cannot be read"),
+            Problem::Mismatch { actual, expected } => write!(
+                f,
+                "lasts {} instead of {}",
+                format_length(*actual),
+                format_length(*expected)
+            ),
+        }
+    }
+}
+
+/// An audio file that failed verification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Broken {
+    pub path: PathBuf,
+    pub problem: Problem,
+    /// What to hand spotdl to download the track again: its Spotify url, or
+    /// its title. `None` if the file matches no song of the playlist.
+    pub query: Option<String>,
+}
+
+/// Outcome of checking a playlist folder.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Report {
+    pub broken: Vec<Broken>,
+    /// Readable files whose length could not be compared, as they match no
+    /// song with a length in `save.spotdl`.
+    pub unchecked: Vec<PathBuf>,
+}
+
+/// Checks every audio file of the playlist folder `dir` against the songs of
+/// its `save.spotdl` file. The expected lengths are the Spotify durations spotdl
+/// saved there, so files it does not list are only checked for being readable.
+pub fn check_playlist(dir: &Path, tolerance: Duration) -> io::Result<Report> {
+    let songs = library::saved_songs(dir);
+    let mut report = Report::default();
+    for path in audio_paths(dir)? {
+        let song = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| find_song(&songs, stem));
+        let expected = song
+            .filter(|song| song.seconds > 0)
+            .map(|song| Duration::from_secs(song.seconds));
+        match check(&path, expected, tolerance) {
+            Some(problem) => report.broken.push(Broken {
+                path,
+                problem,
+                query: song.map(|song| song.url.clone().unwrap_or_else(|| song.title())),
+            }),
+            None if expected.is_none() => report.unchecked.push(path),
+            None => {}
+        }
+    }
+    Ok(report)
+}
+
+/// Checks the file at `path`, comparing its length to `expected` when known.
+/// `None` if nothing is wrong with it.
+pub fn check(path: &Path, expected: Option<Duration>, tolerance: Duration) -> Option<Problem> {
+    let actual = match probe(path) {
+        Ok(actual) => actual,
+        Err(problem) => return Some(problem),
+    };
+    let expected = expected?;
+    (actual.abs_diff(expected) > tolerance).then_some(Problem::Mismatch {
+        actual: actual.as_secs(),
+        expected: expected.as_secs(),
+    })
+}
+
+/// Reads the length of an audio file from its headers.
+pub fn probe(path: &Path) -> Result<Duration, Problem> {
+    let data = fs::read(path).map_err(|err| Problem::Unreadable(err.to_string()))?;
+    if data.is_empty() {
+        return Err(Problem::Empty);
+    }
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_lowercase)
+        .unwrap_or_default();
+    match ext.as_str() {
+        "mp3" => mp3_length(&data),
+        "m4a" => mp4_length(&data),
+        "flac" => flac_length(&data),
+        "ogg" | "opus" => ogg_length(&data),
+        "wav" => wav_length(&data),
+        "aac" => adts_length(&data),
+        _ => Err(Problem::BadHeader),
+    }
+}
+
+/// Audio files under `dir`, sorted.
+fn audio_paths(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let mut paths = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            paths.extend(audio_paths(&path)?);
+            continue;
+        }
+        let audio = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_lowercase().as_str()));
+        if audio {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// The song a file named `stem` was downloaded from. spotdl names files after
+/// the artists and the title, leaving out characters file systems reject, so
+/// only letters and digits are compared.
+fn find_song<'a>(songs: &'a [SavedSong], stem: &str) -> Option<&'a SavedSong> {
+    let stem = simplify(stem);
+    songs
+        .iter()
+        .filter(|song| {
+            let name = simplify(&song.name);
+            !name.is_empty() && stem.contains(&name) && stem.contains(&simplify(&song.artist))
+        })
+        .max_by_key(|song| song.name.len())
+}
+
+fn simplify(text: &str) -> String {
+    text.chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// Length formatted as minutes and seconds, e.g. `3:05`.
+fn format_length(seconds: u64) -> String {
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(at..at + 8)?.try_into().ok()?))
+}
+
+fn le_u32(data: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(at..at + 4)?.try_into().ok()?))
+}
+
+fn seconds(samples: u64, rate: u64) -> Duration {
+    Duration::from_secs_f64(samples as f64 / rate as f64)
+}
+
+/// An MPEG audio layer III frame header.
+struct Mp3Frame {
+    mpeg1: bool,
+    mono: bool,
+    /// In kbps.
+    bitrate: u64,
+    sample_rate: u64,
+}
+
+impl Mp3Frame {
+    fn parse(header: &[u8]) -> Option<Self> {
+        const MPEG1_BITRATES: [u64; 15] = [
+            0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ];
+        const MPEG2_BITRATES: [u64; 15] =
+            [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+        const SAMPLE_RATES: [u64; 3] = [44100, 48000, 32000];
+
+        let [sync, flags, rates, mode] = *header.get(..4)? else {
+            return None;
+        };
+        // Frame sync, then anything but the reserved version and layer III only
+        if sync != 0xFF || flags & 0xE0 != 0xE0 || flags & 0x18 == 0x08 || flags & 0x06 != 0x02 {
+            return None;
+        }
+        let mpeg1 = flags & 0x18 == 0x18;
+        let bitrates = if mpeg1 {
+            MPEG1_BITRATES
+        } else {
+            MPEG2_BITRATES
+        };
+        let bitrate = *bitrates.get((rates >> 4) as usize)?;
+        let sample_rate = *SAMPLE_RATES.get(((rates >> 2) & 0x03) as usize)?;
+        let divisor = match flags & 0x18 {
+            0x18 => 1,
+            0x10 => 2,
+            _ => 4,
+        };
+        (bitrate > 0).then_some(Self {
+            mpeg1,
+            mono: mode >> 6 == 3,
+            bitrate,
+            sample_rate: sample_rate / divisor,
+        })
+    }
+
+    fn samples(&self) -> u64 {
+        if self.mpeg1 {
+            1152
+        } else {
+            576
+        }
+    }
+
+    /// Offset of the Xing header from the start of the frame.
+    fn xing_offset(&self) -> usize {
+        4 + match (self.mpeg1, self.mono) {
+            (true, false) => 32,
+            (true, true) | (false, false) => 17,
+            (false, true) => 9,
+        }
+    }
+}
+
+/// Length from the Xing header of a VBR file, or from the bitrate of the first
+/// frame otherwise.
+fn mp3_length(data: &[u8]) -> Result<Duration, Problem> {
+    let mut start = 0;
+    if data.starts_with(b"ID3") {
+        let size = data
+            .get(6..10)
+            .ok_or(Problem::Truncated)?
+            .iter()
+            .fold(0, |size, byte| size << 7 | (byte & 0x7F) as usize);
+        start = 10 + size;
+        if start >= data.len() {
+            return Err(Problem::Truncated);
+        }
+    }
+    let window = &data[start..data.len().min(start + MP3_SYNC_WINDOW)];
+    let (offset, frame) = (0..window.len())
+        .find_map(|i| Mp3Frame::parse(&window[i..]).map(|frame| (i, frame)))
+        .ok_or(Problem::BadHeader)?;
+    let start = start + offset;
+    let end = if data.len() >= 128 && data[data.len() - 128..].starts_with(b"TAG") {
+        data.len() - 128
+    } else {
+        data.len()
+    };
+
+    let xing = start + frame.xing_offset();
+    if matches!(data.get(xing..xing + 4), Some(b"Xing" | b"Info")) {
+        let flags = be_u32(data, xing + 4).ok_or(Problem::Truncated)?;
+        let mut field = xing + 8;
+        let mut frames = None;
+        if flags & 0x01 != 0 {
+            frames = be_u32(data, field);
+            field += 4;
+        }
+        if flags & 0x02 != 0 {
+            let bytes = be_u32(data, field).ok_or(Problem::Truncated)? as usize;
+            if end - start < bytes {
+                return Err(Problem::Truncated);
+            }
+        }
+        if let Some(frames) = frames {
+            return Ok(seconds(frames as u64 * frame.samples(), frame.sample_rate));
+        }
+    }
+    let bits = (end - start) as u64 * 8;
+    Ok(Duration::from_secs_f64(
+        bits as f64 / (frame.bitrate * 1000) as f64,
+    ))
+}
+
+/// Length from the `mvhd` box of an MP4 file.
+fn mp4_length(data: &[u8]) -> Result<Duration, Problem> {
+    if data.get(4..8) != Some(b"ftyp") {
+        return Err(Problem::BadHeader);
+    }
+    let mut moov = None;
+    let mut pos = 0;
+    while pos + 8 <= data.len() {
+        let (start, size) = match be_u32(data, pos).ok_or(Problem::Truncated)? {
+            0 => (pos + 8, data.len() - pos),
+            1 => {
+                let size = be_u64(data, pos + 8).ok_or(Problem::Truncated)?;
+                (pos + 16, usize::try_from(size).unwrap_or(usize::MAX))
+            }
+            size => (pos + 8, size as usize),
+        };
+        // Compared without adding, a corrupt size could overflow
+        if size < start - pos || size > data.len() - pos {
+            return Err(Problem::Truncated);
+        }
+        if &data[pos + 4..pos + 8] == b"moov" {
+            moov = Some(&data[start..pos + size]);
+        }
+        pos += size;
+    }
+    let moov = moov.ok_or(Problem::NoDuration)?;
+    let mvhd = moov
+        .windows(4)
+        .position(|name| name == b"mvhd")
+        .map(|at| &moov[at + 4..])
+        .ok_or(Problem::NoDuration)?;
+    let (scale, length) = match mvhd.first() {
+        Some(0) => (be_u32(mvhd, 12), be_u32(mvhd, 16).map(u64::from)),
+        Some(1) => (be_u32(mvhd, 20), be_u64(mvhd, 24)),
+        _ => (None, None),
+    };
+    match (scale, length) {
+        (Some(scale), Some(length)) if scale > 0 && length > 0 => Ok(seconds(length, scale as u64)),
+        _ => Err(Problem::NoDuration),
+    }
+}
+
+/// Length from the `STREAMINFO` block of a FLAC file.
+fn flac_length(data: &[u8]) -> Result<Duration, Problem> {
+    if !data.starts_with(b"fLaC") || data.get(4).map(|kind| kind & 0x7F) != Some(0) {
+        return Err(Problem::BadHeader);
+    }
+    let info = data.get(8..26).ok_or(Problem::Truncated)?;
+    let rate = (info[10] as u64) << 12 | (info[11] as u64) << 4 | (info[12] as u64) >> 4;
+    let samples = ((info[13] & 0x0F) as u64) << 32 | be_u32(info, 14).unwrap_or(0) as u64;
+    if rate == 0 || samples == 0 {
+        return Err(Problem::NoDuration);
+    }
+    Ok(seconds(samples, rate))
+}
+
+/// Length from the position of the last page of an Ogg Opus or Vorbis file,
+/// which must be marked as the end of the stream.
+fn ogg_length(data: &[u8]) -> Result<Duration, Problem> {
+    if !data.starts_with(b"OggS") {
+        return Err(Problem::BadHeader);
+    }
+    let packet = 27 + *data.get(26).ok_or(Problem::Truncated)? as usize;
+    let packet = data.get(packet..).ok_or(Problem::Truncated)?;
+    let (rate, skip) = if packet.starts_with(b"OpusHead") {
+        let skip = packet.get(10..12).ok_or(Problem::Truncated)?;
+        (48000, u16::from_le_bytes([skip[0], skip[1]]) as u64)
+    } else if packet.starts_with(b"\x01vorbis") {
+        (le_u32(packet, 12).ok_or(Problem::Truncated)? as u64, 0)
+    } else {
+        return Err(Problem::BadHeader);
+    };
+
+    let last = data
+        .windows(4)
+        .rposition(|magic| magic == b"OggS")
+        .unwrap_or(0);
+    let page = &data[last..];
+    let segments = *page.get(26).ok_or(Problem::Truncated)? as usize;
+    let table = page.get(27..27 + segments).ok_or(Problem::Truncated)?;
+    let size = 27 + segments + table.iter().map(|&len| len as usize).sum::<usize>();
+    // Header type 0x04 marks the last page of the stream
+    if page.len() < size || page[5] & 0x04 == 0 {
+        return Err(Problem::Truncated);
+    }
+    let position = u64::from_le_bytes(page[6..14].try_into().unwrap_or_default());
+    if rate == 0 || position <= skip {
+        return Err(Problem::NoDuration);
+    }
+    Ok(seconds(position - skip, rate))
+}
+
+/// Length from the size of the `data` chunk of a WAV file.
+fn wav_length(data: &[u8]) -> Result<Duration, Problem> {
+    if !data.starts_with(b"RIFF") || data.get(8..12) != Some(b"WAVE") {
+        return Err(Problem::BadHeader);
+    }
+    let mut byte_rate = None;
+    let mut pos = 12;
+    while pos + 8 <= data.len() {
+        let size = le_u32(data, pos + 4).ok_or(Problem::Truncated)? as usize;
+        match &data[pos..pos + 4] {
+            b"fmt " => byte_rate = le_u32(data, pos + 16),
+            b"data" => {
+                if pos + 8 + size > data.len() {
+                    return Err(Problem::Truncated);
+                }
+                return match byte_rate {
+                    Some(rate) if rate > 0 => Ok(seconds(size as u64, rate as u64)),
+                    _ => Err(Problem::NoDuration),
+                };
+            }
+            _ => {}
+        }
+        // Chunks are padded to an even size
+        pos += 8 + size + size % 2;
+    }
+    Err(Problem::NoDuration)
+}
+
+/// Length from adding up the frames of a raw AAC (ADTS) stream.
+fn adts_length(data: &[u8]) -> Result<Duration, Problem> {
+    const SAMPLE_RATES: [u64; 13] = [
+        96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350,
+    ];
+    let mut pos = 0;
+    let mut samples = 0;
+    let mut rate = None;
+    while pos < data.len() {
+        let header = data.get(pos..pos + 7).ok_or(Problem::Truncated)?;
+        if header[0] != 0xFF || header[1] & 0xF0 != 0xF0 {
+            return Err(Problem::BadHeader);
+        }
+        let frame_rate = *SAMPLE_RATES
+            .get(((header[2] >> 2) & 0x0F) as usize)
+            .ok_or(Problem::BadHeader)?;
+        rate.get_or_insert(frame_rate);
+        let len = ((header[3] & 0x03) as usize) << 11
+            | (header[4] as usize) << 3
+            | (header[5] >> 5) as usize;
+        if len < 7 {
+            return Err(Problem::BadHeader);
+        }
+        if pos + len > data.len() {
+            return Err(Problem::Truncated);
+        }
+        samples += 1024 * ((header[6] & 0x03) as u64 + 1);
+        pos += len;
+    }
+    Ok(seconds(samples, rate.ok_or(Problem::NoDuration)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::*;
+    use crate::utils::testing::TestDir;
+
+    /// A CBR MPEG-1 layer III stream at 128 kbps and 44.1 kHz.
+    fn mp3(seconds: usize) -> Vec<u8> {
+        let mut data = b"ID3\x04\x00\x00\x00\x00\x00\x0A".to_vec();
+        data.extend([0; 10]);
+        let frame = [0xFF, 0xFB, 0x90, 0x00];
+        let mut audio = vec![0; seconds * 16000];
+        audio[..4].copy_from_slice(&frame);
+        data.extend(audio);
+        data
+    }
+
+    /// Mono 8 kHz 8-bit PCM announcing `seconds` of audio but holding `kept` bytes.
+    fn wav(seconds: u32, kept: usize) -> Vec<u8> {
+        let mut data = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+        data.extend(16u32.to_le_bytes());
+        data.extend([1, 0, 1, 0]);
+        data.extend(8000u32.to_le_bytes());
+        data.extend(8000u32.to_le_bytes());
+        data.extend([1, 0, 8, 0]);
+        data.extend(b"data");
+        data.extend((seconds * 8000).to_le_bytes());
+        data.extend(vec![0x80; kept]);
+        data
+    }
+
+    fn flac(samples: u32) -> Vec<u8> {
+        let mut data = b"fLaC\x80\x00\x00\x22".to_vec();
+        data.extend([0; 10]);
+        // 44100 Hz, stereo, 16 bits per sample
+        data.extend([0x0A, 0xC4, 0x42, 0xF0]);
+        data.extend(samples.to_be_bytes());
+        data.extend([0; 16]);
+        data
+    }
+
+    #[test]
+    fn test_probe() -> io::Result<()> {
+        let root = TestDir::new("probe");
+        let files = [
+            ("song.mp3", mp3(3)),
+            ("song.wav", wav(2, 16000)),
+            ("song.flac", flac(441000)),
+            ("cut.wav", wav(2, 100)),
+            ("empty.m4a", vec![]),
+            ("noise.mp3", vec![0x42; 4096]),
+            (
+                "stream.m4a",
+                b"\0\0\0\x10ftypM4A \0\0\0\0\0\0\x10\0mdat".to_vec(),
+            ),
+            // A 64-bit box size far past the end of the file
+            (
+                "huge.m4a",
+                b"\0\0\0\x08ftyp\0\0\0\x01moov\xff\xff\xff\xff\xff\xff\xff\xff".to_vec(),
+            ),
+        ];
+        for (name, data) in &files {
+            fs::write(root.join(name), data)?;
+        }
+
+        let probe = |name: &str| probe(&root.join(name)).map(|length| length.as_secs());
+        assert_eq!(probe("song.mp3"), Ok(3));
+        assert_eq!(probe("song.wav"), Ok(2));
+        assert_eq!(probe("song.flac"), Ok(10));
+        assert_eq!(probe("cut.wav"), Err(Problem::Truncated));
+        assert_eq!(probe("empty.m4a"), Err(Problem::Empty));
+        assert_eq!(probe("noise.mp3"), Err(Problem::BadHeader));
+        assert_eq!(probe("stream.m4a"), Err(Problem::Truncated));
+        assert_eq!(probe("huge.m4a"), Err(Problem::Truncated));
+        Ok(())
+    }
+
+    #[test]
+    fn test_check_playlist() -> io::Result<()> {
+        let dir = TestDir::new("verify");
+        let save = serde_json::json!({"songs": [
+            {"name": "Calm", "artist": "Band", "duration": 3, "url": "https://open.spotify.com/track/a"},
+            {"name": "Slow", "artist": "Band", "duration": 60},
+        ]});
+        fs::write(dir.join("save.spotdl"), save.to_string())?;
+        fs::write(dir.join("Band - Calm.mp3"), mp3(3))?;
+        fs::write(dir.join("Band - Slow.mp3"), mp3(4))?;
+        fs::write(dir.join("Other.mp3"), b"")?;
+        fs::write(dir.join("Unknown.mp3"), mp3(3))?;
+
+        assert_eq!(
+            check_playlist(&dir, DEFAULT_TOLERANCE)?,
+            Report {
+                broken: vec![
+                    Broken {
+                        path: dir.join("Band - Slow.mp3"),
+                        problem: Problem::Mismatch {
+                            actual: 4,
+                            expected: 60
+                        },
+                        query: Some("Band - Slow".to_string()),
+                    },
+                    Broken {
+                        path: dir.join("Other.mp3"),
+                        problem: Problem::Empty,
+                        query: None,
+                    },
+                ],
+                unchecked: vec![dir.join("Unknown.mp3")],
+            }
+        );
+        Ok(())
+    }
+}